@@ -0,0 +1,43 @@
+//! Timings of sphere intersection and of a small full render, run with
+//! `cargo bench`. The scenes are fixed, so results are comparable between
+//! runs.
+
+extern crate raytracer;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use raytracer::scenes::fractal;
+use raytracer::{render, Camera, Material, Ray, RenderConfig, Sphere, Vector3d};
+
+/// Calls `f` repeatedly for about a second and prints the mean time per call.
+fn bench<T, F: FnMut() -> T>(name: &str, mut f: F) {
+    black_box(f());
+    let mut iterations: u32 = 0;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        iterations += 1;
+    }
+    println!("{:<20} {:>12?} per iteration ({} iterations)", name, start.elapsed() / iterations, iterations);
+}
+
+fn main() {
+    let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, Material::default());
+    let rays: Vec<Ray> = (0..1024)
+        .map(|i| {
+            let (x, y) = (f64::from(i % 32) / 16.0 - 1.0, f64::from(i / 32) / 16.0 - 1.0);
+            Ray::new(Vector3d::ZERO, Vector3d::new(x * 0.3, y * 0.3, 1.0).normalize())
+        })
+        .collect();
+    bench("ray_sphere x1024", || rays.iter().map(|ray| sphere.ray_sphere(black_box(ray))).sum::<f64>());
+
+    let scene = fractal(6, Vector3d::new(0.0, -1.0, 0.0), 1.0);
+    let camera = Camera::new(
+        Vector3d::new(0.0, 0.0, -4.0),
+        Vector3d::new(0.0, 0.0, 1.0),
+        Vector3d::new(0.0, 1.0, 0.0),
+        (2.0 * 0.5f64.atan()).to_degrees());
+    let config = RenderConfig::default().width(64).height(64).samples(2).threads(1);
+    bench("render 64x64", || render(scene.as_ref(), &camera, &config));
+}
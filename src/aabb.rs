@@ -0,0 +1,123 @@
+use vector3d::Vector3d;
+use Ray;
+
+const INF: Vector3d = Vector3d { x: f64::INFINITY, y: f64::INFINITY, z: f64::INFINITY };
+const NEG_INF: Vector3d = Vector3d { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY, z: f64::NEG_INFINITY };
+
+/// An axis-aligned bounding box: the points between `min` and `max`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3d,
+    pub max: Vector3d,
+}
+
+impl Aabb {
+    /// Contains nothing; the union with it leaves a box unchanged.
+    pub const EMPTY: Aabb = Aabb { min: INF, max: NEG_INF };
+    /// Contains everything, for unbounded objects such as planes.
+    pub const INFINITE: Aabb = Aabb { min: NEG_INF, max: INF };
+
+    pub fn new(min: Vector3d, max: Vector3d) -> Self {
+        Aabb { min, max }
+    }
+
+    /// The box reaching `extent` from `center` in each direction.
+    pub fn around(center: Vector3d, extent: Vector3d) -> Self {
+        Aabb { min: center - extent, max: center + extent }
+    }
+
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn contains(&self, point: Vector3d) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x &&
+            self.min.y <= point.y && point.y <= self.max.y &&
+            self.min.z <= point.z && point.z <= self.max.z
+    }
+
+    pub fn centroid(&self) -> Vector3d {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns whether all coordinates are finite, i.e. the box is neither
+    /// unbounded nor empty.
+    pub fn is_finite(&self) -> bool {
+        self.min.to_array().iter().chain(self.max.to_array().iter()).all(|v| v.is_finite())
+    }
+
+    /// Returns one of the eight corners, taking the `max` coordinate along
+    /// x, y and z where bit 0, 1 and 2 of `k` are set.
+    pub fn corner(&self, k: usize) -> Vector3d {
+        Vector3d::new(
+            if k & 1 == 0 { self.min.x } else { self.max.x },
+            if k & 2 == 0 { self.min.y } else { self.max.y },
+            if k & 4 == 0 { self.min.z } else { self.max.z })
+    }
+
+    /// Returns the distances at which the ray enters and leaves the box;
+    /// the ray misses when the entry lies beyond the exit, as it always
+    /// does for an empty box such as `EMPTY`.
+    pub fn slabs(&self, ray: &Ray) -> (f64, f64) {
+        let mut t_near: f64 = f64::NEG_INFINITY;
+        let mut t_far: f64 = f64::INFINITY;
+        for axis in 0..3 {
+            if self.min[axis] > self.max[axis] {
+                return (f64::INFINITY, f64::NEG_INFINITY);
+            }
+            let inv: f64 = 1.0 / ray.dir[axis];
+            let t0: f64 = (self.min[axis] - ray.orig[axis]) * inv;
+            let t1: f64 = (self.max[axis] - ray.orig[axis]) * inv;
+            t_near = t_near.max(t0.min(t1));
+            t_far = t_far.min(t0.max(t1));
+        }
+        (t_near, t_far)
+    }
+
+    /// Returns whether the ray passes through the box ahead of its origin.
+    pub fn ray_intersects(&self, ray: &Ray) -> bool {
+        let (t_near, t_far) = self.slabs(ray);
+        t_near <= t_far && t_far >= 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit() -> Aabb {
+        Aabb::new(Vector3d::new(-1.0, -1.0, -1.0), Vector3d::ONE)
+    }
+
+    #[test]
+    fn union_covers_both_boxes() {
+        let other = Aabb::new(Vector3d::new(0.0, 2.0, -3.0), Vector3d::new(0.5, 4.0, 0.0));
+        assert_eq!(unit().union(other), Aabb::new(Vector3d::new(-1.0, -1.0, -3.0), Vector3d::new(1.0, 4.0, 1.0)));
+        assert_eq!(unit().union(Aabb::EMPTY), unit());
+        assert_eq!(Aabb::EMPTY.union(unit()), unit());
+        assert_eq!(unit().union(Aabb::INFINITE), Aabb::INFINITE);
+    }
+
+    #[test]
+    fn ray_intersects_box_ahead() {
+        let z = Vector3d::new(0.0, 0.0, 1.0);
+        assert!(unit().ray_intersects(&Ray::new(Vector3d::new(0.0, 0.0, -5.0), z)));
+        assert!(unit().ray_intersects(&Ray::new(Vector3d::ZERO, z)));
+        // Parallel to an axis, beside the box
+        assert!(!unit().ray_intersects(&Ray::new(Vector3d::new(2.0, 0.0, -5.0), z)));
+        // The box lies behind the origin
+        assert!(!unit().ray_intersects(&Ray::new(Vector3d::new(0.0, 0.0, 5.0), z)));
+        let diagonal = Vector3d::new(1.0, 1.0, 1.0).normalize();
+        assert!(unit().ray_intersects(&Ray::new(Vector3d::new(-3.0, -3.0, -3.0), diagonal)));
+        assert!(!unit().ray_intersects(&Ray::new(Vector3d::new(-3.0, 0.0, -6.0), diagonal)));
+    }
+
+    #[test]
+    fn empty_box_is_never_hit() {
+        let ray = Ray::new(Vector3d::new(0.0, 0.0, -5.0), Vector3d::new(0.0, 0.0, 1.0));
+        assert!(!Aabb::EMPTY.ray_intersects(&ray));
+        assert!(Aabb::INFINITE.ray_intersects(&ray));
+        let (t_near, t_far) = Aabb::EMPTY.slabs(&ray);
+        assert!(t_near > t_far);
+    }
+}
@@ -0,0 +1,115 @@
+use std::mem;
+
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisAlignedBox {
+    min: Vector3d,
+    max: Vector3d,
+    material: Material,
+}
+
+impl AxisAlignedBox {
+    pub fn new(min: Vector3d, max: Vector3d, material: Material) -> Self {
+        AxisAlignedBox { min: min.min(max), max: min.max(max), material }
+    }
+
+    /// Slab intersection, returning the nearest positive distance together
+    /// with the normal of the face that was hit.
+    pub fn ray_box(&self, ray: &Ray) -> (f64, Vector3d) {
+        let mut t_near: f64 = f64::NEG_INFINITY;
+        let mut t_far: f64 = f64::INFINITY;
+        let mut near_axis: usize = 0;
+        let mut far_axis: usize = 0;
+        for axis in 0..3 {
+            let inv: f64 = 1.0 / ray.dir[axis];
+            let mut t0: f64 = (self.min[axis] - ray.orig[axis]) * inv;
+            let mut t1: f64 = (self.max[axis] - ray.orig[axis]) * inv;
+            if t0 > t1 {
+                mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > t_near {
+                t_near = t0;
+                near_axis = axis;
+            }
+            if t1 < t_far {
+                t_far = t1;
+                far_axis = axis;
+            }
+        }
+        if t_near > t_far || t_far <= f64::EPSILON {
+            return (f64::INFINITY, Vector3d::ZERO);
+        }
+        let (t, axis, sign) = if t_near > f64::EPSILON {
+            (t_near, near_axis, -ray.dir[near_axis].signum())
+        } else {
+            (t_far, far_axis, ray.dir[far_axis].signum())
+        };
+        let mut normal: Vector3d = Vector3d::ZERO;
+        normal[axis] = sign;
+        (t, normal)
+    }
+}
+
+impl Scene for AxisAlignedBox {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let (l, n) = self.ray_box(ray);
+        if l == f64::INFINITY {
+            None
+        } else {
+            Some(Hit::new(l, n, self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_box(ray).0 < f64::INFINITY
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn unit_box() -> AxisAlignedBox {
+        AxisAlignedBox::new(Vector3d::new(-1.0, -1.0, -1.0), Vector3d::ONE, Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_enters_through_x_face() {
+        let ray = Ray::new(Vector3d::new(5.0, 0.5, 0.0), Vector3d::new(-1.0, 0.0, 0.0));
+        let hit: Hit = unit_box().intersect(&miss(), &ray);
+        assert_eq!(hit.lambda, 4.0);
+        assert_eq!(hit.normal, Vector3d::new(1.0, 0.0, 0.0));
+        assert!(unit_box().shadow(&ray));
+    }
+
+    #[test]
+    fn ray_from_inside_leaves_through_face() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let hit: Hit = unit_box().intersect(&miss(), &ray);
+        assert_eq!(hit.lambda, 1.0);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_misses_box() {
+        let ray = Ray::new(Vector3d::new(5.0, 2.0, 0.0), Vector3d::new(-1.0, 0.0, 0.0));
+        assert_eq!(unit_box().intersect(&miss(), &ray), miss());
+        assert!(!unit_box().shadow(&ray));
+        let away = Ray::new(Vector3d::new(5.0, 0.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(unit_box().intersect(&miss(), &away), miss());
+        assert_eq!(unit_box().bounding_box(), Aabb::new(Vector3d::new(-1.0, -1.0, -1.0), Vector3d::ONE));
+    }
+}
@@ -0,0 +1,238 @@
+use std::f64::consts;
+
+use light::hash_unit;
+use vector3d::Vector3d;
+use {Aabb, Ray};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(tag = "type", rename_all = "snake_case"))]
+pub enum Projection {
+    /// Rays fan out from the camera position; `fov_degrees` is the vertical
+    /// field of view.
+    Perspective { fov_degrees: f64 },
+    /// Parallel rays along `forward` from an image plane `width` units wide.
+    Orthographic { width: f64 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+pub struct Camera {
+    position: Vector3d,
+    forward: Vector3d,
+    up: Vector3d,
+    projection: Projection,
+    /// Diameter of the lens; 0 gives a pinhole camera with everything in
+    /// focus.
+    #[cfg_attr(feature = "serde", serde(default))]
+    aperture: f64,
+    /// Distance along `forward` to the plane that is in perfect focus, which
+    /// must be positive when `aperture` is.
+    #[cfg_attr(feature = "serde", serde(default))]
+    focus_distance: f64,
+}
+
+impl Camera {
+    /// Creates a perspective camera with the given vertical field of view.
+    pub fn new(position: Vector3d, forward: Vector3d, up: Vector3d, fov_degrees: f64) -> Self {
+        Camera::with_projection(position, forward, up, Projection::Perspective { fov_degrees })
+    }
+
+    pub fn with_projection(position: Vector3d, forward: Vector3d, up: Vector3d, projection: Projection) -> Self {
+        Camera { position, forward, up, projection, aperture: 0.0, focus_distance: 0.0 }
+    }
+
+    /// Adds defocus blur from a lens of the given diameter, focused at
+    /// `focus_distance`.
+    ///
+    /// Panics if `aperture` is positive and `focus_distance` is not.
+    pub fn with_depth_of_field(self, aperture: f64, focus_distance: f64) -> Self {
+        assert!(aperture <= 0.0 || focus_distance > 0.0, "focus distance must be positive with an aperture");
+        Camera { aperture, focus_distance, ..self }
+    }
+
+    pub fn aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    pub fn focus_distance(&self) -> f64 {
+        self.focus_distance
+    }
+
+    /// Returns the camera rotated by `angle_radians` around the line through
+    /// `center` along the (normalized) `axis`, as for a turntable animation.
+    pub fn orbit(self, center: Vector3d, axis: Vector3d, angle_radians: f64) -> Self {
+        Camera {
+            position: center + (self.position - center).rotate_around_axis(axis, angle_radians),
+            forward: self.forward.rotate_around_axis(axis, angle_radians),
+            up: self.up.rotate_around_axis(axis, angle_radians),
+            ..self
+        }
+    }
+
+    /// Returns the normalized forward, right and up vectors.
+    fn basis(&self) -> (Vector3d, Vector3d, Vector3d) {
+        let forward: Vector3d = self.forward.normalize();
+        let right: Vector3d = self.up.cross(forward).normalize();
+        let up: Vector3d = forward.cross(right);
+        (forward, right, up)
+    }
+
+    /// Returns the pixel position, with `y` increasing upwards, where
+    /// `point` appears in the image, or `None` if it lies behind the camera.
+    pub fn project(&self, point: Vector3d, width: u32, height: u32) -> Option<(f64, f64)> {
+        let (forward, right, up) = self.basis();
+        let v: Vector3d = point - self.position;
+        let depth: f64 = v.dot(forward);
+        let scale: f64 = match self.projection {
+            Projection::Perspective { fov_degrees } if depth > f64::EPSILON =>
+                f64::from(height) * 0.5 / (fov_degrees.to_radians() * 0.5).tan() / depth,
+            Projection::Orthographic { width: extent } if depth >= 0.0 => f64::from(width) / extent,
+            _ => return None,
+        };
+        Some((f64::from(width) * 0.5 + v.dot(right) * scale, f64::from(height) * 0.5 + v.dot(up) * scale))
+    }
+
+    /// Returns the pixel rectangle `(x0, y0, x1, y1)`, with `y` increasing
+    /// upwards, outside of which no primary ray can hit the box.
+    /// Returns `None` when no such bound is known: for unbounded boxes, boxes
+    /// reaching behind the camera, or with depth of field.
+    pub fn screen_bounds(&self, bounds: Aabb, width: u32, height: u32) -> Option<(f64, f64, f64, f64)> {
+        if self.aperture > 0.0 {
+            return None;
+        }
+        let mut rect = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for k in 0..8 {
+            let (x, y) = self.project(bounds.corner(k), width, height)?;
+            if !(x.is_finite() && y.is_finite()) {
+                return None;
+            }
+            rect = (rect.0.min(x), rect.1.min(y), rect.2.max(x), rect.3.max(y));
+        }
+        Some(rect)
+    }
+
+    /// Builds the primary ray through the (sub)pixel `(x, y)`, where `y`
+    /// increases upwards.
+    pub fn ray_for_pixel(&self, x: f64, y: f64, width: u32, height: u32) -> Ray {
+        let (forward, right, up) = self.basis();
+        let (dx, dy) = (x - f64::from(width) * 0.5, y - f64::from(height) * 0.5);
+        let pinhole: Ray = match self.projection {
+            Projection::Perspective { fov_degrees } => {
+                let focal: f64 = f64::from(height) * 0.5 / (fov_degrees.to_radians() * 0.5).tan();
+                let d: Vector3d = forward * focal + right * dx + up * dy;
+                Ray::new(self.position, d.normalize())
+            }
+            Projection::Orthographic { width: extent } => {
+                let scale: f64 = extent / f64::from(width);
+                Ray::new(self.position + right * (dx * scale) + up * (dy * scale), forward)
+            }
+        };
+        if self.aperture <= 0.0 {
+            return pinhole;
+        }
+        // Rays through the same subpixel all meet on the focal plane. The
+        // point on the lens is picked by hashing the subpixel position, so
+        // that the supersamples of a pixel spread over the lens.
+        let focus: Vector3d = pinhole.orig + pinhole.dir * (self.focus_distance / pinhole.dir.dot(forward));
+        let r: f64 = self.aperture * 0.5 * hash_unit(Vector3d::new(x, y, 0.0)).sqrt();
+        let theta: f64 = hash_unit(Vector3d::new(y, x, 1.0)) * 2.0 * consts::PI;
+        let origin: Vector3d = pinhole.orig + right * (r * theta.cos()) + up * (r * theta.sin());
+        Ray::new(origin, (focus - origin).normalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Hit, Material, Scene, Sphere};
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn camera() -> Camera {
+        Camera::new(Vector3d::new(0.0, 1.0, -4.0), Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0)
+    }
+
+    #[test]
+    fn center_ray_points_forward() {
+        let camera = Camera::new(Vector3d::new(1.0, 2.0, 3.0), Vector3d::new(0.0, 0.0, -2.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        let ray: Ray = camera.ray_for_pixel(32.0, 24.0, 64, 48);
+        assert_eq!(ray.orig, Vector3d::new(1.0, 2.0, 3.0));
+        assert!(ray.dir.distance(Vector3d::new(0.0, 0.0, -1.0)) < 1e-12);
+        // The top edge of the image lies half the field of view up
+        let top: Ray = camera.ray_for_pixel(32.0, 48.0, 64, 48);
+        assert!((top.dir.angle_between(ray.dir) - 30f64.to_radians()).abs() < 1e-12);
+        assert!(top.dir.y > 0.0);
+    }
+
+    #[test]
+    fn zero_aperture_is_a_pinhole() {
+        let lens = camera().with_depth_of_field(0.0, 4.0);
+        for &(x, y) in &[(0.5, 0.5), (10.25, 3.75), (31.5, 23.5)] {
+            assert_eq!(lens.ray_for_pixel(x, y, 32, 24), camera().ray_for_pixel(x, y, 32, 24));
+        }
+    }
+
+    #[test]
+    fn large_aperture_spreads_ray_origins() {
+        let lens = camera().with_depth_of_field(2.0, 4.0);
+        let xs: Vec<f64> = (0..16).map(|k| 16.0 + f64::from(k) / 16.0).collect();
+        let first: Vector3d = lens.ray_for_pixel(xs[0], 12.0, 32, 24).orig;
+        assert!(xs.iter().any(|&x| (lens.ray_for_pixel(x, 12.0, 32, 24).orig - first).length() > 0.1));
+        // Every ray starts on the lens and meets the pinhole ray on the
+        // focal plane
+        for &x in &xs {
+            let ray: Ray = lens.ray_for_pixel(x, 12.0, 32, 24);
+            let pinhole: Ray = camera().ray_for_pixel(x, 12.0, 32, 24);
+            assert!((ray.orig - pinhole.orig).length() <= 1.0 + 1e-9);
+            let focus: Vector3d = pinhole.orig + pinhole.dir * (4.0 / pinhole.dir.z);
+            assert!((ray.orig + ray.dir * ((focus.z - ray.orig.z) / ray.dir.z) - focus).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "focus distance")]
+    fn aperture_needs_focus_distance() {
+        camera().with_depth_of_field(0.5, 0.0);
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let camera = Camera::with_projection(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0),
+                                             Projection::Orthographic { width: 4.0 });
+        let (a, b) = (camera.ray_for_pixel(0.0, 0.0, 40, 20), camera.ray_for_pixel(40.0, 20.0, 40, 20));
+        assert_eq!((a.dir, b.dir), (Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 0.0, 1.0)));
+        assert_eq!(a.orig, Vector3d::new(-2.0, -1.0, 0.0));
+        assert_eq!(b.orig, Vector3d::new(2.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn orthographic_size_does_not_depend_on_depth() {
+        let columns = |camera: &Camera, z: f64| {
+            let sphere = Sphere::new(Vector3d::new(0.0, 0.0, z), 1.0, Material::default());
+            let miss = Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default());
+            (0..40).filter(|&x| sphere.intersect(&miss, &camera.ray_for_pixel(f64::from(x) + 0.5, 10.5, 40, 20)) != miss).count()
+        };
+        let at = |projection| Camera::with_projection(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), projection);
+        let ortho = at(Projection::Orthographic { width: 8.0 });
+        assert_eq!(columns(&ortho, 5.0), 10);
+        assert_eq!(columns(&ortho, 20.0), 10);
+        let perspective = at(Projection::Perspective { fov_degrees: 60.0 });
+        assert!(columns(&perspective, 5.0) > columns(&perspective, 20.0));
+    }
+
+    #[test]
+    fn larger_field_of_view_shows_sphere_smaller() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 10.0), 1.0, Material::default());
+        let covered = |fov: f64| {
+            let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), fov);
+            (0..40 * 30).filter(|&i| sphere.intersect(&miss(), &camera.ray_for_pixel(f64::from(i % 40) + 0.5, f64::from(i / 40) + 0.5, 40, 30)) != miss()).count()
+        };
+        let (narrow, wide) = (covered(30.0), covered(60.0));
+        assert!(wide > 0 && wide * 3 < narrow, "{} {}", narrow, wide);
+    }
+}
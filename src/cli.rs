@@ -0,0 +1,293 @@
+use std::str::FromStr;
+
+use raytracer::{Background, Integrator, PpmFormat, Projection, RenderConfig, RenderMode, SampleMode, SamplePattern, ToneMap, Vector3d};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub render: RenderConfig,
+    pub level: i32,
+    pub output: String,
+    /// JSON scene file rendered instead of the sphere fractal.
+    pub scene: Option<String>,
+    pub projection: Projection,
+    /// Lens diameter and focus distance of the camera.
+    pub aperture: f64,
+    pub focus_distance: f64,
+    pub ppm_format: PpmFormat,
+    /// Number of frames of a turntable animation, `None` renders a single
+    /// image.
+    pub frames: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            render: RenderConfig { tile_size: 32, ..RenderConfig::default() },
+            level: 9,
+            output: String::from("image.ppm"),
+            scene: None,
+            // The focal length equals the image height
+            projection: Projection::Perspective { fov_degrees: (2.0 * 0.5f64.atan()).to_degrees() },
+            aperture: 0.0,
+            focus_distance: 4.0,
+            ppm_format: PpmFormat::Binary,
+            frames: None,
+        }
+    }
+}
+
+pub fn usage(program: &str) -> String {
+    format!("Usage: {} [OPTIONS]
+  --width N                image width in pixels (512)
+  --height N               image height in pixels (512)
+  --level N                recursion level of the sphere fractal (9)
+  --scene FILE             render a JSON scene file instead of the fractal,
+                           - reads it from standard input
+  --fov DEGREES            vertical field of view of the camera (53.13)
+  --ortho WIDTH            orthographic projection showing WIDTH units
+  --aperture A             lens diameter for depth of field (0)
+  --focus D                distance to the plane in focus (4)
+  --samples N              supersampling grid size per axis (4)
+  --jitter SEED            jitter the supersampling grid with a seeded RNG
+  --pattern PATTERN        grid, rotated or halton[:BX,BY] placement of the
+                           supersamples (grid; BX,BY 2,3)
+  --adaptive DEPTH,T       adaptive sampling, subdividing up to DEPTH times
+                           while the corner color variance exceeds T
+  --path-trace N           path trace with N paths per sample instead of
+                           Whitted ray tracing
+  --seed S                 seed of the random paths (0)
+  --depth N                maximum reflection or path depth (1)
+  --mode MODE              shaded, normal, depth[:FAR] with FAR the
+                           distance that maps to black, or wireframe[:W]
+                           with black triangle edges W wide in barycentric
+                           coordinates (shaded; FAR 10, W 0.02)
+  --exposure E             multiplier of the linear color (1.0)
+  --tone-map none|reinhard compression of bright colors (none)
+  --gamma G                output gamma, 1.0 disables correction (2.2)
+  --background R,G,B|sky   color of rays that miss (0,0,0)
+  --ambient R,G,B          ambient light color (0,0,0)
+  --indirect F             weight of a diffuse bounce per hit (0)
+  --fog DENSITY            exponential distance fog density (0)
+  --fog-color R,G,B        color distant objects fade to (0,0,0)
+  --shadow-bias B          relative offset of secondary rays (1e-9)
+  --threads N              worker threads, 0 uses all cores (0)
+  --tile N                 size of the tiles rendered by the threads, 0
+                           renders whole rows (32)
+  --output FILE            output file, PPM or PNG by extension, - writes
+                           PPM to standard output (image.ppm)
+  --frames N               render N frames of the camera circling the scene
+                           to FILE with the frame number appended
+  --ppm binary|ascii       PPM encoding, P6 or P3 (binary)", program)
+}
+
+/// Parses the command-line arguments, excluding the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut args = args.into_iter();
+    let mut jitter_seed = None;
+    let mut pattern = None;
+    let mut path_seed = 0;
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--width" => config.render.width = parse_value(&arg, &value)?,
+            "--height" => config.render.height = parse_value(&arg, &value)?,
+            "--level" => config.level = parse_value(&arg, &value)?,
+            "--scene" => config.scene = Some(value),
+            "--fov" => config.projection = parse_fov(&value)?,
+            "--ortho" => config.projection = parse_ortho(&value)?,
+            "--aperture" => config.aperture = parse_value(&arg, &value)?,
+            "--focus" => config.focus_distance = parse_value(&arg, &value)?,
+            "--samples" => config.render.sampling = SampleMode::Uniform(parse_value(&arg, &value)?),
+            "--jitter" => jitter_seed = Some(parse_value(&arg, &value)?),
+            "--pattern" => pattern = Some(parse_pattern(&value)?),
+            "--adaptive" => config.render.sampling = parse_adaptive(&value)?,
+            "--path-trace" => config.render.integrator = Integrator::PathTrace { samples_per_pixel: parse_value(&arg, &value)?, seed: 0 },
+            "--seed" => path_seed = parse_value(&arg, &value)?,
+            "--depth" => config.render.max_depth = parse_value(&arg, &value)?,
+            "--mode" => config.render.mode = parse_mode(&value)?,
+            "--exposure" => config.render.exposure = parse_value(&arg, &value)?,
+            "--tone-map" => config.render.tone_map = parse_tone_map(&value)?,
+            "--gamma" => config.render.gamma = parse_value(&arg, &value)?,
+            "--background" => config.render.background = parse_background(&value)?,
+            "--ambient" => config.render.ambient = parse_vector(&arg, &value)?,
+            "--indirect" => config.render.indirect = parse_value(&arg, &value)?,
+            "--fog" => config.render.fog_density = parse_value(&arg, &value)?,
+            "--fog-color" => config.render.fog_color = parse_vector(&arg, &value)?,
+            "--shadow-bias" => config.render.shadow_bias = parse_value(&arg, &value)?,
+            "--threads" => config.render.threads = parse_value(&arg, &value)?,
+            "--tile" => config.render.tile_size = parse_value(&arg, &value)?,
+            "--output" => config.output = value,
+            "--frames" => config.frames = Some(parse_value(&arg, &value)?),
+            "--ppm" => config.ppm_format = parse_ppm_format(&value)?,
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    if let Integrator::PathTrace { ref mut seed, .. } = config.render.integrator {
+        *seed = path_seed;
+    }
+    if let Some(seed) = jitter_seed {
+        match config.render.sampling {
+            SampleMode::Uniform(samples) => config.render.sampling = SampleMode::Jittered { samples, seed },
+            _ => return Err(String::from("--jitter cannot be combined with --adaptive")),
+        }
+    }
+    if let Some(pattern) = pattern {
+        match config.render.sampling {
+            SampleMode::Uniform(samples) => config.render.sampling = SampleMode::Pattern { pattern, samples },
+            _ => return Err(String::from("--pattern cannot be combined with --jitter or --adaptive")),
+        }
+    }
+    if config.level < 1 {
+        return Err(String::from("Level must be positive"));
+    }
+    if config.frames == Some(0) {
+        return Err(String::from("Frames must be positive"));
+    }
+    if config.frames.is_some() && config.output == "-" {
+        return Err(String::from("--frames cannot write to standard output"));
+    }
+    if config.aperture.is_nan() || config.aperture < 0.0 || config.focus_distance.is_nan() || config.focus_distance <= 0.0 {
+        return Err(String::from("Aperture must be non-negative and focus distance positive"));
+    }
+    config.render = config.render.build()?;
+    Ok(config)
+}
+
+fn parse_background(value: &str) -> Result<Background, String> {
+    if value == "sky" {
+        return Ok(Background::Gradient {
+            bottom: Vector3d::ONE,
+            top: Vector3d::new(0.5, 0.7, 1.0),
+        });
+    }
+    parse_vector("--background", value).map(Background::Solid)
+}
+
+fn parse_adaptive(value: &str) -> Result<SampleMode, String> {
+    let invalid = || format!("Invalid value for --adaptive: {}", value);
+    let (depth, threshold) = value.split_once(',').ok_or_else(invalid)?;
+    let max_depth: u32 = depth.parse().map_err(|_| invalid())?;
+    let threshold: f64 = threshold.parse().map_err(|_| invalid())?;
+    if threshold.is_nan() || threshold < 0.0 {
+        return Err(invalid());
+    }
+    Ok(SampleMode::Adaptive { max_depth, threshold })
+}
+
+fn parse_fov(value: &str) -> Result<Projection, String> {
+    match value.parse::<f64>() {
+        Ok(fov_degrees) if fov_degrees > 0.0 && fov_degrees < 180.0 => Ok(Projection::Perspective { fov_degrees }),
+        _ => Err(format!("Invalid value for --fov: {}", value)),
+    }
+}
+
+fn parse_pattern(value: &str) -> Result<SamplePattern, String> {
+    let invalid = || format!("Invalid value for --pattern: {}", value);
+    match value.split_once(':') {
+        None if value == "grid" => Ok(SamplePattern::Grid),
+        None if value == "rotated" => Ok(SamplePattern::RotatedGrid),
+        None if value == "halton" => Ok(SamplePattern::Halton(2, 3)),
+        Some(("halton", bases)) => {
+            let (x, y) = bases.split_once(',').ok_or_else(invalid)?;
+            match (x.parse::<u32>(), y.parse::<u32>()) {
+                (Ok(x), Ok(y)) if x >= 2 && y >= 2 => Ok(SamplePattern::Halton(x, y)),
+                _ => Err(invalid()),
+            }
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_ortho(value: &str) -> Result<Projection, String> {
+    match value.parse::<f64>() {
+        Ok(width) if width > 0.0 => Ok(Projection::Orthographic { width }),
+        _ => Err(format!("Invalid value for --ortho: {}", value)),
+    }
+}
+
+fn parse_mode(value: &str) -> Result<RenderMode, String> {
+    let invalid = || format!("Invalid value for --mode: {}", value);
+    match value.split_once(':') {
+        None if value == "shaded" => Ok(RenderMode::Shaded),
+        None if value == "normal" => Ok(RenderMode::Normal),
+        None if value == "depth" => Ok(RenderMode::Depth { far: 10.0 }),
+        Some(("depth", far)) => match far.parse::<f64>() {
+            Ok(far) if far > 0.0 => Ok(RenderMode::Depth { far }),
+            _ => Err(invalid()),
+        },
+        None if value == "wireframe" => Ok(RenderMode::Wireframe { width: 0.02, color: Vector3d::ZERO }),
+        Some(("wireframe", width)) => match width.parse::<f64>() {
+            Ok(width) if width > 0.0 => Ok(RenderMode::Wireframe { width, color: Vector3d::ZERO }),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_tone_map(value: &str) -> Result<ToneMap, String> {
+    match value {
+        "none" => Ok(ToneMap::None),
+        "reinhard" => Ok(ToneMap::Reinhard),
+        _ => Err(format!("Invalid value for --tone-map: {}", value)),
+    }
+}
+
+fn parse_ppm_format(value: &str) -> Result<PpmFormat, String> {
+    match value {
+        "binary" => Ok(PpmFormat::Binary),
+        "ascii" => Ok(PpmFormat::Ascii),
+        _ => Err(format!("Invalid value for --ppm: {}", value)),
+    }
+}
+
+fn parse_vector(arg: &str, value: &str) -> Result<Vector3d, String> {
+    let components = value
+        .split(',')
+        .map(|c| parse_value::<f64>(arg, c))
+        .collect::<Result<Vec<f64>, String>>()?;
+    if components.len() != 3 {
+        return Err(format!("Invalid value for {}: {}", arg, value));
+    }
+    Ok(Vector3d::new(components[0], components[1], components[2]))
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", arg, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let config: Config = parse_args(args("")).unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!((config.render.width, config.render.height, config.level), (512, 512, 9));
+        assert_eq!(config.render.sampling, SampleMode::Uniform(4));
+        assert_eq!(config.output, "image.ppm");
+    }
+
+    #[test]
+    fn parses_size_level_samples_and_output() {
+        let config: Config = parse_args(args("--width 64 --height 32 --level 3 --samples 2 --output out.ppm")).unwrap();
+        assert_eq!((config.render.width, config.render.height, config.level), (64, 32, 3));
+        assert_eq!(config.render.sampling, SampleMode::Uniform(2));
+        assert_eq!(config.output, "out.ppm");
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse_args(args("--width abc")).is_err());
+        assert!(parse_args(args("--width -3")).is_err());
+        assert!(parse_args(args("--level 0")).is_err());
+        assert!(parse_args(args("--samples 0")).is_err());
+        assert!(parse_args(args("--colour red")).unwrap_err().contains("--colour"));
+        assert!(parse_args(args("--width")).unwrap_err().contains("Missing value"));
+    }
+}
@@ -0,0 +1,131 @@
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+/// The lateral surface of a cone, open at its base, opening up from `apex`
+/// along `axis` with the given half angle (radians) up to `height`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cone {
+    apex: Vector3d,
+    axis: Vector3d,
+    half_angle: f64,
+    height: f64,
+    material: Material,
+}
+
+impl Cone {
+    pub fn new(apex: Vector3d, axis: Vector3d, half_angle: f64, height: f64, material: Material) -> Self {
+        Cone { apex, axis: axis.normalize(), half_angle, height, material }
+    }
+
+    pub fn ray_cone(&self, ray: &Ray) -> f64 {
+        let cos2: f64 = self.half_angle.cos().powi(2);
+        let co: Vector3d = ray.orig - self.apex;
+        let dv: f64 = ray.dir.dot(self.axis);
+        let ov: f64 = co.dot(self.axis);
+        let a: f64 = dv * dv - cos2 * ray.dir.dot(ray.dir);
+        let b: f64 = dv * ov - cos2 * ray.dir.dot(co);
+        let c: f64 = ov * ov - cos2 * co.dot(co);
+        let roots: [f64; 2] = if a.abs() < f64::EPSILON {
+            // The ray runs parallel to the surface, which it crosses only
+            // once, where the equation is linear
+            if b == 0.0 {
+                return f64::INFINITY;
+            }
+            [-c / (2.0 * b); 2]
+        } else {
+            let disc: f64 = b * b - a * c;
+            if disc < 0.0 {
+                return f64::INFINITY;
+            }
+            let sq: f64 = disc.sqrt();
+            let (t1, t2) = ((-b - sq) / a, (-b + sq) / a);
+            [t1.min(t2), t1.max(t2)]
+        };
+        // The equation also describes the mirrored cone behind the apex,
+        // which is cut away together with everything beyond `height`.
+        roots
+            .iter()
+            .cloned()
+            .find(|&t| {
+                let h: f64 = ov + t * dv;
+                t > f64::EPSILON && (0.0..=self.height).contains(&h)
+            })
+            .unwrap_or(f64::INFINITY)
+    }
+}
+
+impl Scene for Cone {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_cone(ray);
+        if l == f64::INFINITY {
+            None
+        } else {
+            let v: Vector3d = ray.orig + ray.dir * l - self.apex;
+            let n: Vector3d = v * (v.dot(self.axis) / v.dot(v)) - self.axis;
+            Some(Hit::new(l, n.normalize(), self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_cone(ray) < f64::INFINITY
+    }
+
+    /// Encloses the apex and the rim of the base.
+    fn bounding_box(&self) -> Aabb {
+        let base: Vector3d = self.apex + self.axis * self.height;
+        let r: f64 = self.height * self.half_angle.tan();
+        let a: Vector3d = self.axis;
+        let extent = Vector3d::new(
+            r * (1.0 - a.x * a.x).max(0.0).sqrt(),
+            r * (1.0 - a.y * a.y).max(0.0).sqrt(),
+            r * (1.0 - a.z * a.z).max(0.0).sqrt());
+        Aabb::new((base - extent).min(self.apex), (base + extent).max(self.apex))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn cone() -> Cone {
+        Cone::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), consts::FRAC_PI_4, 2.0, Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_hits_lateral_surface() {
+        let ray = Ray::new(Vector3d::new(-3.0, 1.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        let hit: Hit = cone().intersect(&miss(), &ray);
+        assert!((hit.lambda - 2.0).abs() < 1e-9);
+        let n: Vector3d = hit.normal;
+        let outward = Vector3d::new(-1.0, -1.0, 0.0).normalize();
+        assert!((n - outward).length() < 1e-9, "{:?}", n);
+        assert!(cone().shadow(&ray));
+    }
+
+    #[test]
+    fn ray_outside_cone_angle_misses() {
+        // Passes above the rim, and below the apex through the mirrored cone
+        let above = Ray::new(Vector3d::new(-3.0, 2.5, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(cone().intersect(&miss(), &above), miss());
+        let below = Ray::new(Vector3d::new(-3.0, -1.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(cone().intersect(&miss(), &below), miss());
+        // Beside the cone
+        let beside = Ray::new(Vector3d::new(-3.0, 1.0, 1.5), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(cone().intersect(&miss(), &beside), miss());
+    }
+
+    #[test]
+    fn ray_parallel_to_surface_hits_once() {
+        let ray = Ray::new(Vector3d::new(-1.0, 0.0, 0.0), Vector3d::new(1.0, 1.0, 0.0).normalize());
+        let hit: Hit = cone().intersect(&miss(), &ray);
+        assert!((hit.lambda - consts::FRAC_1_SQRT_2).abs() < 1e-9);
+    }
+}
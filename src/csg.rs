@@ -0,0 +1,135 @@
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+use {Aabb, Hit, Ray, Scene};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(rename_all = "snake_case"))]
+pub enum CsgOp {
+    /// The part of `a` outside `b`.
+    Difference,
+    /// The part inside both `a` and `b`.
+    Intersection,
+}
+
+impl CsgOp {
+    fn contains(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Difference => in_a && !in_b,
+            CsgOp::Intersection => in_a && in_b,
+        }
+    }
+}
+
+/// Constructive solid geometry on two closed objects, such as spheres,
+/// boxes or groups of them, whose normals point outwards.
+pub struct Csg {
+    op: CsgOp,
+    a: Box<dyn Scene>,
+    b: Box<dyn Scene>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Box<dyn Scene>, b: Box<dyn Scene>) -> Self {
+        Csg { op, a, b }
+    }
+}
+
+/// Returns the first hit of `scene` along the ray beyond distance `t`.
+fn hit_after(scene: &dyn Scene, ray: &Ray, t: f64) -> Option<Hit> {
+    // Step past the previous surface so that it is not found again
+    let start: f64 = t + 1e-9 * (1.0 + t + ray.orig.length());
+    let hit: Hit = scene.hit(&Ray::new(ray.orig + ray.dir * start, ray.dir))?;
+    Some(Hit { lambda: start + hit.lambda, ..hit })
+}
+
+impl Scene for Csg {
+    /// Walks the surfaces of both objects along the ray, tracking whether
+    /// the ray is inside each, until it crosses the boundary of the
+    /// combined solid. Surfaces of `b` bounding a difference keep their
+    /// material, with the normal turned around.
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        let mut next_a: Option<Hit> = hit_after(self.a.as_ref(), ray, 0.0);
+        let mut next_b: Option<Hit> = hit_after(self.b.as_ref(), ray, 0.0);
+        // The ray starts inside an object if its first hit is an exit
+        let mut in_a: bool = next_a.as_ref().is_some_and(|hit| hit.normal.dot(ray.dir) > 0.0);
+        let mut in_b: bool = next_b.as_ref().is_some_and(|hit| hit.normal.dot(ray.dir) > 0.0);
+        loop {
+            let from_a: bool = match (&next_a, &next_b) {
+                (None, None) => return None,
+                (Some(a), Some(b)) => a.lambda <= b.lambda,
+                (a, _) => a.is_some(),
+            };
+            let hit: Hit = if from_a { next_a.take() } else { next_b.take() }.unwrap();
+            let inside: bool = self.op.contains(in_a, in_b);
+            let entering: bool = hit.normal.dot(ray.dir) < 0.0;
+            if from_a {
+                in_a = entering;
+                next_a = hit_after(self.a.as_ref(), ray, hit.lambda);
+            } else {
+                in_b = entering;
+                next_b = hit_after(self.b.as_ref(), ray, hit.lambda);
+            }
+            if self.op.contains(in_a, in_b) != inside {
+                return Some(if !from_a && self.op == CsgOp::Difference {
+                    Hit { normal: -hit.normal, ..hit }
+                } else {
+                    hit
+                });
+            }
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let a: Aabb = self.a.bounding_box();
+        match self.op {
+            CsgOp::Difference => a,
+            CsgOp::Intersection => {
+                let b: Aabb = self.b.bounding_box();
+                Aabb::new(a.min.max(b.min), a.max.min(b.max))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vector3d::Vector3d;
+    use {Material, Sphere};
+
+    fn bitten(op: CsgOp) -> Csg {
+        Csg::new(op,
+                 Box::new(Sphere::new(Vector3d::ZERO, 1.0, Material::default())),
+                 Box::new(Sphere::new(Vector3d::new(1.0, 0.0, 0.0), 0.8, Material::default())))
+    }
+
+    #[test]
+    fn difference_hits_the_carved_surface() {
+        let hit: Hit = bitten(CsgOp::Difference).hit(&Ray::new(Vector3d::new(5.0, 0.0, 0.0), Vector3d::new(-1.0, 0.0, 0.0))).unwrap();
+        assert!((hit.lambda - 4.8).abs() < 1e-9);
+        assert!(hit.normal.distance(Vector3d::new(1.0, 0.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn difference_misses_inside_the_removed_region() {
+        let ray = Ray::new(Vector3d::new(0.9, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert!(Sphere::new(Vector3d::ZERO, 1.0, Material::default()).hit(&ray).is_some());
+        assert_eq!(bitten(CsgOp::Difference).hit(&ray), None);
+        assert!(!bitten(CsgOp::Difference).shadow(&ray));
+    }
+
+    #[test]
+    fn intersection_is_entered_through_either_surface() {
+        let hit: Hit = bitten(CsgOp::Intersection).hit(&Ray::new(Vector3d::new(5.0, 0.0, 0.0), Vector3d::new(-1.0, 0.0, 0.0))).unwrap();
+        assert!((hit.lambda - 4.0).abs() < 1e-9);
+        assert!(hit.normal.distance(Vector3d::new(1.0, 0.0, 0.0)) < 1e-9);
+        let hit: Hit = bitten(CsgOp::Intersection).hit(&Ray::new(Vector3d::new(-5.0, 0.0, 0.0), Vector3d::new(1.0, 0.0, 0.0))).unwrap();
+        assert!((hit.lambda - 5.2).abs() < 1e-9);
+        assert!(hit.normal.distance(Vector3d::new(-1.0, 0.0, 0.0)) < 1e-9);
+    }
+}
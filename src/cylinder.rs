@@ -0,0 +1,101 @@
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cylinder {
+    base: Vector3d,
+    axis: Vector3d,
+    radius: f64,
+    material: Material,
+}
+
+impl Cylinder {
+    pub fn new(base: Vector3d, axis: Vector3d, radius: f64, material: Material) -> Self {
+        Cylinder { base, axis: axis.normalize(), radius, material }
+    }
+
+    pub fn ray_cylinder(&self, ray: &Ray) -> f64 {
+        let d: Vector3d = ray.dir.reject_from(self.axis);
+        let o: Vector3d = (ray.orig - self.base).reject_from(self.axis);
+        let a: f64 = d.dot(d);
+        if a < f64::EPSILON {
+            return f64::INFINITY;
+        }
+        let b: f64 = d.dot(o);
+        let disc: f64 = b * b - a * (o.dot(o) - self.radius * self.radius);
+        if disc < 0.0 {
+            return f64::INFINITY;
+        }
+        let sq: f64 = disc.sqrt();
+        let t1: f64 = (-b - sq) / a;
+        let t2: f64 = (-b + sq) / a;
+        if t1 > f64::EPSILON {
+            t1
+        } else if t2 > f64::EPSILON {
+            t2
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+impl Scene for Cylinder {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_cylinder(ray);
+        if l == f64::INFINITY {
+            None
+        } else {
+            let n: Vector3d = (ray.orig + ray.dir * l - self.base).reject_from(self.axis);
+            Some(Hit::new(l, n.normalize(), self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_cylinder(ray) < f64::INFINITY
+    }
+
+    /// The cylinder is infinitely long, so like `Plane` it reports an
+    /// unbounded box, which every ray passes in the slab test of its
+    /// `Group`.
+    fn bounding_box(&self) -> Aabb {
+        Aabb::INFINITE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(Vector3d::ZERO, Vector3d::new(0.0, 2.0, 0.0), 1.0, Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn perpendicular_ray_hits_side() {
+        let ray = Ray::new(Vector3d::new(-5.0, 10.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        let hit: Hit = cylinder().intersect(&miss(), &ray);
+        assert_eq!(hit.lambda, 4.0);
+        assert_eq!(hit.normal, Vector3d::new(-1.0, 0.0, 0.0));
+        assert!(cylinder().shadow(&ray));
+        let inside = Ray::new(Vector3d::new(0.0, -3.0, 0.0), Vector3d::new(0.0, 0.0, 1.0));
+        let hit: Hit = cylinder().intersect(&miss(), &inside);
+        assert_eq!(hit.lambda, 1.0);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn rays_beside_and_along_axis_miss() {
+        let beside = Ray::new(Vector3d::new(-5.0, 0.0, 1.5), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(cylinder().intersect(&miss(), &beside), miss());
+        assert!(!cylinder().shadow(&beside));
+        let along = Ray::new(Vector3d::new(0.5, 0.0, 0.0), Vector3d::new(0.0, 1.0, 0.0));
+        assert_eq!(cylinder().intersect(&miss(), &along), miss());
+    }
+}
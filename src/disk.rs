@@ -0,0 +1,84 @@
+use plane::Plane;
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disk {
+    center: Vector3d,
+    normal: Vector3d,
+    radius: f64,
+    material: Material,
+}
+
+impl Disk {
+    pub fn new(center: Vector3d, normal: Vector3d, radius: f64, material: Material) -> Self {
+        Disk { center, normal: normal.normalize(), radius, material }
+    }
+
+    pub fn ray_disk(&self, ray: &Ray) -> f64 {
+        let plane = Plane::new(self.center, self.normal, self.material.clone());
+        let t: f64 = plane.ray_plane(ray);
+        if t < f64::INFINITY &&
+            (ray.orig + ray.dir * t).distance_squared(self.center) <= self.radius * self.radius {
+            t
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+impl Scene for Disk {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_disk(ray);
+        if l == f64::INFINITY {
+            None
+        } else if ray.dir.dot(self.normal) > 0.0 {
+            Some(Hit::new(l, -self.normal, self.material.clone()))
+        } else {
+            Some(Hit::new(l, self.normal, self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_disk(ray) < f64::INFINITY
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::around(self.center, Vector3d::new(self.radius, self.radius, self.radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn disk() -> Disk {
+        Disk::new(Vector3d::new(0.0, 1.0, 0.0), Vector3d::new(0.0, 1.0, 0.0), 2.0, Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_hits_center() {
+        let ray = Ray::new(Vector3d::new(0.0, 4.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        let hit: Hit = disk().intersect(&miss(), &ray);
+        assert_eq!(hit.lambda, 3.0);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 1.0, 0.0));
+        assert!(disk().shadow(&ray));
+    }
+
+    #[test]
+    fn ray_just_outside_radius_misses() {
+        let ray = Ray::new(Vector3d::new(2.01, 4.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert_eq!(disk().intersect(&miss(), &ray), miss());
+        assert!(!disk().shadow(&ray));
+        let inside = Ray::new(Vector3d::new(1.99, 4.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert!(disk().intersect(&miss(), &inside) != miss());
+        assert_eq!(disk().bounding_box(), Aabb::new(Vector3d::new(-2.0, -1.0, -2.0), Vector3d::new(2.0, 3.0, 2.0)));
+    }
+}
@@ -0,0 +1,376 @@
+use {Aabb, Hit, Ray, Scene};
+
+/// Number of children up to which a `Group` sorts them along the ray, in a
+/// buffer on the stack. Larger groups visit them in order.
+const SORTED_CHILDREN: usize = 16;
+
+pub struct Group {
+    bbox: Aabb,
+    objects: Vec<Box<dyn Scene>>,
+    /// Bounding boxes of the objects, in the same order.
+    boxes: Vec<Aabb>,
+}
+
+impl Group {
+    /// Objects with an infinite bounding box (such as planes) make the group
+    /// unbounded, in which case every ray is tested against the children.
+    pub fn new(objects: Vec<Box<dyn Scene>>) -> Self {
+        let boxes: Vec<Aabb> = objects.iter().map(|scene| scene.bounding_box()).collect();
+        let bbox: Aabb = boxes.iter().fold(Aabb::EMPTY, |a, &b| a.union(b));
+        Group { bbox, objects, boxes }
+    }
+}
+
+impl Scene for Group {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        Some(self.intersect(&Hit::MISS, ray)).filter(|hit| !hit.is_miss())
+    }
+
+    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
+        let (t_near, t_far) = self.bbox.slabs(ray);
+        if t_near > t_far || t_far < 0.0 || t_near >= i.lambda {
+            return i.clone();
+        }
+        let entries = self.boxes
+            .iter()
+            .enumerate()
+            .filter_map(|(k, bounds)| {
+                let (t_near, t_far) = bounds.slabs(ray);
+                if t_near > t_far || t_far < 0.0 || t_near >= i.lambda {
+                    None
+                } else {
+                    Some((t_near, k))
+                }
+            });
+        let mut out: Hit = i.clone();
+        if self.objects.len() > SORTED_CHILDREN {
+            for (t_near, k) in entries {
+                if t_near < out.lambda {
+                    out = self.objects[k].intersect(&out, ray);
+                }
+            }
+            return out;
+        }
+        // Visit the objects in the order in which the ray enters their
+        // boxes, so that near hits cut off the objects further away
+        let mut order: [(f64, usize); SORTED_CHILDREN] = [(0.0, 0); SORTED_CHILDREN];
+        let mut n: usize = 0;
+        for entry in entries {
+            let mut j: usize = n;
+            while j > 0 && order[j - 1].0 > entry.0 {
+                order[j] = order[j - 1];
+                j -= 1;
+            }
+            order[j] = entry;
+            n += 1;
+        }
+        for &(t_near, k) in &order[..n] {
+            if t_near >= out.lambda {
+                break;
+            }
+            out = self.objects[k].intersect(&out, ray);
+        }
+        out
+    }
+
+    fn intersect_all(&self, ray: &Ray, out: &mut Vec<Hit>) {
+        if self.bbox.ray_intersects(ray) {
+            for (scene, bounds) in self.objects.iter().zip(&self.boxes) {
+                if bounds.ray_intersects(ray) {
+                    scene.intersect_all(ray, out);
+                }
+            }
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        if self.bbox.ray_intersects(ray) {
+            for scene in &self.objects {
+                if scene.shadow(ray) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Number of objects up to which a `Bvh` leaf keeps them in a `Group`,
+/// which sorts them along the ray more cheaply than further splits.
+const LEAF_SIZE: usize = 8;
+
+/// Bounding volume hierarchy over a set of objects. Children are split in
+/// halves by the centroid of their bounding box along the longest axis of
+/// the node, so that whole subtrees can be skipped. Objects without a finite
+/// bounding box, such as planes, stay out of the split and are kept in a
+/// flat group next to the tree.
+pub enum Bvh {
+    Leaf(Box<dyn Scene>),
+    Node {
+        bounds: Aabb,
+        left: Box<Bvh>,
+        right: Box<Bvh>,
+    },
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn Scene>>) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects.into_iter().partition(|scene| scene.bounding_box().is_finite());
+        if bounded.is_empty() {
+            return Bvh::Leaf(Box::new(Group::new(unbounded)));
+        }
+        let tree: Bvh = Bvh::split(bounded);
+        if unbounded.is_empty() {
+            return tree;
+        }
+        let unbounded: Group = Group::new(unbounded);
+        Bvh::Node {
+            bounds: tree.bounding_box().union(unbounded.bounding_box()),
+            left: Box::new(tree),
+            right: Box::new(Bvh::Leaf(Box::new(unbounded))),
+        }
+    }
+
+    fn split(mut objects: Vec<Box<dyn Scene>>) -> Self {
+        if objects.len() == 1 {
+            return Bvh::Leaf(objects.pop().unwrap());
+        }
+        if objects.len() <= LEAF_SIZE {
+            return Bvh::Leaf(Box::new(Group::new(objects)));
+        }
+        let bounds: Aabb = objects.iter().fold(Aabb::EMPTY, |a, scene| a.union(scene.bounding_box()));
+        let (axis, _) = (bounds.max - bounds.min).max_component();
+        let centroid = |scene: &dyn Scene| scene.bounding_box().centroid()[axis];
+        objects.sort_by(|a, b| centroid(a.as_ref()).total_cmp(&centroid(b.as_ref())));
+        let right: Vec<Box<dyn Scene>> = objects.split_off(objects.len() / 2);
+        Bvh::Node {
+            bounds,
+            left: Box::new(Bvh::split(objects)),
+            right: Box::new(Bvh::split(right)),
+        }
+    }
+
+    /// Distance at which the ray enters this node's box, or infinity when
+    /// it misses.
+    fn entry(&self, ray: &Ray) -> f64 {
+        match *self {
+            Bvh::Leaf(_) => 0.0,
+            Bvh::Node { bounds, .. } => {
+                let (t_near, t_far) = bounds.slabs(ray);
+                if t_near > t_far || t_far < 0.0 {
+                    f64::INFINITY
+                } else {
+                    t_near
+                }
+            }
+        }
+    }
+
+    /// Like `intersect`, for a ray known to enter this node's box before
+    /// the nearest hit so far, so that every box is slab-tested once.
+    fn intersect_entered(&self, i: &Hit, ray: &Ray) -> Hit {
+        match *self {
+            Bvh::Leaf(ref scene) => scene.intersect(i, ray),
+            Bvh::Node { ref left, ref right, .. } => {
+                let (t_left, t_right) = (left.entry(ray), right.entry(ray));
+                let ((near, t_near), (far, t_far)) = if t_left <= t_right {
+                    ((left, t_left), (right, t_right))
+                } else {
+                    ((right, t_right), (left, t_left))
+                };
+                let mut out: Hit = i.clone();
+                if t_near < out.lambda {
+                    out = near.intersect_entered(&out, ray);
+                }
+                if t_far < out.lambda {
+                    out = far.intersect_entered(&out, ray);
+                }
+                out
+            }
+        }
+    }
+}
+
+impl Scene for Bvh {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        Some(self.intersect(&Hit::MISS, ray)).filter(|hit| !hit.is_miss())
+    }
+
+    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
+        if self.entry(ray) >= i.lambda {
+            i.clone()
+        } else {
+            self.intersect_entered(i, ray)
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        match *self {
+            Bvh::Leaf(ref scene) => scene.shadow(ray),
+            Bvh::Node { ref left, ref right, .. } => {
+                self.entry(ray) < f64::INFINITY && (left.shadow(ray) || right.shadow(ray))
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match *self {
+            Bvh::Leaf(ref scene) => scene.bounding_box(),
+            Bvh::Node { bounds, .. } => bounds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use stats;
+    use vector3d::Vector3d;
+    use {Material, Plane, Sphere};
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    /// A sphere that counts the intersection tests done against it.
+    struct Counted(Sphere, &'static AtomicUsize);
+
+    impl Scene for Counted {
+        fn hit(&self, ray: &Ray) -> Option<Hit> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.hit(ray)
+        }
+
+        fn shadow(&self, ray: &Ray) -> bool {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.shadow(ray)
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            self.0.bounding_box()
+        }
+    }
+
+    fn grid_of_spheres(tests: &'static AtomicUsize) -> Vec<Box<dyn Scene>> {
+        (0..100)
+            .map(|k| {
+                let center = Vector3d::new(f64::from(k % 10) * 2.0, f64::from(k / 10) * 2.0, f64::from(k % 7));
+                Box::new(Counted(Sphere::new(center, 0.8, Material::from_color(Vector3d::ONE)), tests)) as Box<dyn Scene>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bvh_finds_same_hits_with_fewer_tests() {
+        static FLAT_TESTS: AtomicUsize = AtomicUsize::new(0);
+        static BVH_TESTS: AtomicUsize = AtomicUsize::new(0);
+        let flat = Group::new(grid_of_spheres(&FLAT_TESTS));
+        let bvh = Bvh::new(grid_of_spheres(&BVH_TESTS));
+        assert_eq!(bvh.bounding_box(), flat.bounding_box());
+        for k in 0..50 {
+            let target = Vector3d::new(f64::from(k % 10) * 2.1, f64::from(k / 5) * 1.9, 3.0);
+            let ray = Ray::new(Vector3d::new(9.0, 9.0, -20.0), (target - Vector3d::new(9.0, 9.0, -20.0)).normalize());
+            let expected = (flat.intersect(&miss(), &ray), flat.shadow(&ray));
+            assert_eq!((bvh.intersect(&miss(), &ray), bvh.shadow(&ray)), expected);
+        }
+        let (flat_tests, bvh_tests) = (FLAT_TESTS.load(Ordering::Relaxed), BVH_TESTS.load(Ordering::Relaxed));
+        assert!(bvh_tests < flat_tests, "{} tests in the Bvh, {} in the Group", bvh_tests, flat_tests);
+    }
+
+    #[test]
+    fn bvh_keeps_unbounded_objects_out_of_the_split() {
+        static TESTS: AtomicUsize = AtomicUsize::new(0);
+        let floor = || Box::new(Plane::new(Vector3d::new(0.0, -1.0, 0.0), Vector3d::new(0.0, 1.0, 0.0), Material::default()));
+        let with_floor = || -> Vec<Box<dyn Scene>> {
+            let mut objects: Vec<Box<dyn Scene>> = grid_of_spheres(&TESTS);
+            objects.insert(50, floor());
+            objects
+        };
+        let (flat, bvh) = (Group::new(with_floor()), Bvh::new(with_floor()));
+        assert_eq!(bvh.bounding_box(), flat.bounding_box());
+        for k in 0..50 {
+            // The lower rays pass below the spheres onto the floor
+            let target = Vector3d::new(f64::from(k % 10) * 2.1, f64::from(k / 5) * 1.9 - 9.0, 3.0);
+            let ray = Ray::new(Vector3d::new(9.0, 9.0, -20.0), (target - Vector3d::new(9.0, 9.0, -20.0)).normalize());
+            assert_eq!((bvh.hit(&ray), bvh.shadow(&ray)), (flat.hit(&ray), flat.shadow(&ray)));
+        }
+        let down = Ray::new(Vector3d::new(30.0, 4.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert_eq!(bvh.hit(&down).unwrap().lambda, 5.0);
+        assert_eq!(Bvh::new(vec![floor()]).hit(&down).unwrap().lambda, 5.0);
+        assert_eq!(Bvh::new(Vec::new()).hit(&down), None);
+    }
+
+    #[test]
+    fn cached_box_equals_union_of_children() {
+        static TESTS: AtomicUsize = AtomicUsize::new(0);
+        let fresh: Aabb = grid_of_spheres(&TESTS).iter().fold(Aabb::EMPTY, |a, scene| a.union(scene.bounding_box()));
+        let outer = Group::new(vec![Box::new(Group::new(grid_of_spheres(&TESTS))), Box::new(row_of_spheres())]);
+        assert_eq!(Group::new(grid_of_spheres(&TESTS)).bounding_box(), fresh);
+        assert_eq!(outer.bounding_box(), fresh.union(row_of_spheres().bounding_box()));
+        assert_eq!(Group::new(Vec::new()).bounding_box(), Aabb::EMPTY);
+    }
+
+    fn row_of_spheres() -> Group {
+        Group::new((0..10)
+            .map(|i| Box::new(Sphere::new(Vector3d::new(f64::from(i), 0.0, 0.0), 0.5, Material::from_color(Vector3d::ONE))) as Box<dyn Scene>)
+            .collect())
+    }
+
+    #[test]
+    fn group_of_missing_objects_reports_a_miss() {
+        let ray = Ray::new(Vector3d::new(0.0, 2.0, -5.0), Vector3d::new(0.0, 0.0, 1.0));
+        let group: Group = row_of_spheres();
+        assert_eq!(group.hit(&ray), None);
+        assert!(group.intersect(&Hit::MISS, &ray).is_miss());
+        let bvh = Bvh::new((0..10).map(|i| Box::new(Sphere::new(Vector3d::new(f64::from(i), 0.0, 0.0), 0.5, Material::default())) as Box<dyn Scene>).collect());
+        assert!(bvh.intersect(&Hit::MISS, &ray).is_miss());
+        assert!(Hit::MISS.is_miss() && !Hit::new(1.0, Vector3d::ZERO, Material::default()).is_miss());
+    }
+
+    #[test]
+    fn front_to_back_order_needs_fewer_tests() {
+        let spheres: Vec<Sphere> = (0..10).rev()
+            .map(|i| Sphere::new(Vector3d::new(0.6 * f64::from(i), 0.0, 0.0), 0.5, Material::default()))
+            .collect();
+        let ray = Ray::new(Vector3d::new(-5.0, 0.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        stats::take();
+        let nearest: f64 = spheres.iter().filter_map(|sphere| sphere.hit(&ray)).map(|hit| hit.lambda).fold(f64::INFINITY, f64::min);
+        assert_eq!(stats::take().intersection_tests, 10);
+        let group = Group::new(spheres.into_iter().map(|sphere| Box::new(sphere) as Box<dyn Scene>).collect());
+        assert_eq!(group.hit(&ray).unwrap().lambda, nearest);
+        assert_eq!(stats::take().intersection_tests, 1);
+    }
+
+    #[test]
+    fn large_group_finds_the_nearest_hit() {
+        let spheres: Vec<Sphere> = (0..40).rev()
+            .map(|i| Sphere::new(Vector3d::new(0.6 * f64::from(i), 0.0, 0.0), 0.5, Material::default()))
+            .collect();
+        let group = Group::new(spheres.iter().map(|sphere| Box::new(sphere.clone()) as Box<dyn Scene>).collect());
+        for &(x, dx) in &[(-5.0, 1.0), (30.0, -1.0)] {
+            let ray = Ray::new(Vector3d::new(x, 0.0, 0.0), Vector3d::new(dx, 0.0, 0.0));
+            let nearest: f64 = spheres.iter().filter_map(|sphere| sphere.hit(&ray)).map(|hit| hit.lambda).fold(f64::INFINITY, f64::min);
+            assert_eq!(group.hit(&ray).unwrap().lambda, nearest);
+        }
+    }
+
+    #[test]
+    fn box_rejects_rays_inside_bounding_sphere() {
+        let group = row_of_spheres();
+        // The ray passes through the sphere around the row, but above its box
+        let ray = Ray::new(Vector3d::new(-1.0, 1.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        stats::take();
+        assert_eq!(group.hit(&ray), None);
+        assert!(!group.shadow(&ray));
+        assert_eq!(stats::take().intersection_tests, 0);
+
+        let ray = Ray::new(Vector3d::new(4.0, 3.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert_eq!(group.hit(&ray).unwrap().lambda, 2.5);
+        assert!(stats::take().intersection_tests > 0);
+    }
+}
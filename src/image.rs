@@ -0,0 +1,203 @@
+use std::convert::TryFrom;
+use std::io;
+use std::io::{BufRead, Write};
+use std::str;
+#[cfg(feature = "png")]
+use std::path::Path;
+
+/// A rendered image, stored row by row from the top-left pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Image { width, height, pixels: vec![[0; 3]; width as usize * height as usize] }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pixels.as_flattened()
+    }
+
+    /// Counts the pixels that have a channel at the end of the range, as a
+    /// sign of over- or underexposure.
+    pub fn clipping(&self) -> Clipping {
+        Clipping {
+            clipped_high: self.pixels.iter().filter(|p| p.contains(&255)).count(),
+            clipped_low: self.pixels.iter().filter(|p| p.contains(&0)).count(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Clipping {
+    /// Pixels with at least one channel at 255.
+    pub clipped_high: usize,
+    /// Pixels with at least one channel at 0.
+    pub clipped_low: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PpmFormat {
+    /// Raw bytes (`P6`).
+    #[default]
+    Binary,
+    /// Decimal values, one pixel per line (`P3`).
+    Ascii,
+}
+
+/// Writes the image as a PPM file in the given format.
+pub fn write_ppm<W: Write>(img: &Image, format: PpmFormat, w: &mut W) -> io::Result<()> {
+    match format {
+        PpmFormat::Binary => {
+            write!(w, "P6\n{} {}\n255\n", img.width, img.height)?;
+            w.write_all(img.as_bytes())
+        }
+        PpmFormat::Ascii => {
+            write!(w, "P3\n{} {}\n255\n", img.width, img.height)?;
+            for [r, g, b] in &img.pixels {
+                writeln!(w, "{} {} {}", r, g, b)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads a PPM file in either format with a maximum value of 255.
+pub fn read_ppm<R: BufRead>(r: &mut R) -> io::Result<Image> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut data: Vec<u8> = Vec::new();
+    r.read_to_end(&mut data)?;
+    // The header is four whitespace-separated tokens, with comments running
+    // from '#' to the end of the line
+    let mut tokens: Vec<&[u8]> = Vec::new();
+    let mut pos: usize = 0;
+    while tokens.len() < 4 && pos < data.len() {
+        match data[pos] {
+            b'#' => pos += data[pos..].iter().position(|&c| c == b'\n').unwrap_or(data.len() - pos),
+            c if c.is_ascii_whitespace() => pos += 1,
+            _ => {
+                let end: usize = data[pos..].iter().position(|c| c.is_ascii_whitespace()).map_or(data.len(), |n| pos + n);
+                tokens.push(&data[pos..end]);
+                pos = end;
+            }
+        }
+    }
+    let number = |token: &[u8]| str::from_utf8(token).ok().and_then(|t| t.parse::<u32>().ok());
+    let (width, height, max) = match tokens[..] {
+        [_, w, h, m] => (number(w), number(h), number(m)),
+        _ => return Err(invalid("Truncated PPM header")),
+    };
+    let (width, height) = match (width, height, max) {
+        (Some(w), Some(h), Some(255)) => (w, h),
+        _ => return Err(invalid("Unsupported PPM header")),
+    };
+    // Every channel takes at least one byte, so a header promising more
+    // channels than there is data left is rejected before allocating
+    let count: usize = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(3))
+        .filter(|&n| n <= data.len() - pos)
+        .ok_or_else(|| invalid("Truncated PPM data"))?;
+    let mut img = Image::new(width, height);
+    let bytes: Vec<u8> = match tokens[0] {
+        // A single whitespace byte separates the header from the raw bytes
+        b"P6" => data.get(pos + 1..pos + 1 + count).ok_or_else(|| invalid("Truncated PPM data"))?.to_vec(),
+        b"P3" => data[pos..]
+            .split(|c| c.is_ascii_whitespace())
+            .filter(|t| !t.is_empty())
+            .take(count)
+            .map(|t| number(t).and_then(|v| u8::try_from(v).ok()).ok_or_else(|| invalid("Invalid PPM value")))
+            .collect::<io::Result<Vec<u8>>>()?,
+        _ => return Err(invalid("Not a PPM file")),
+    };
+    if bytes.len() != count {
+        return Err(invalid("Truncated PPM data"));
+    }
+    for (pixel, rgb) in img.pixels.iter_mut().zip(bytes.chunks(3)) {
+        pixel.copy_from_slice(rgb);
+    }
+    Ok(img)
+}
+
+/// Reads a PNG file, dropping any alpha channel.
+#[cfg(feature = "png")]
+pub fn read_png(path: &Path) -> io::Result<Image> {
+    let rgb = image_crate::open(path).map_err(io::Error::other)?.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let mut img = Image::new(width, height);
+    for (pixel, rgb) in img.pixels.iter_mut().zip(rgb.pixels()) {
+        *pixel = rgb.0;
+    }
+    Ok(img)
+}
+
+/// Writes the image as a PNG file.
+#[cfg(feature = "png")]
+pub fn write_png(img: &Image, path: &Path) -> io::Result<()> {
+    image_crate::save_buffer_with_format(
+        path,
+        img.as_bytes(),
+        img.width,
+        img.height,
+        image_crate::ExtendedColorType::Rgb8,
+        image_crate::ImageFormat::Png)
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> Image {
+        let mut img = Image::new(width, height);
+        for (k, pixel) in img.pixels.iter_mut().enumerate() {
+            *pixel = [k as u8, (k * 7) as u8, 255 - k as u8];
+        }
+        img
+    }
+
+    #[test]
+    fn write_binary_ppm() {
+        let mut out: Vec<u8> = Vec::new();
+        write_ppm(&gradient(2, 1), PpmFormat::Binary, &mut out).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\x00\x00\xff\x01\x07\xfe");
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn png_round_trip() {
+        let path = std::env::temp_dir().join(format!("raytracer-test-{}.png", std::process::id()));
+        write_png(&gradient(5, 3), &path).unwrap();
+        let decoded = image_crate::open(&path).map(|decoded| decoded.to_rgb8());
+        std::fs::remove_file(&path).unwrap();
+        let decoded = decoded.unwrap();
+        assert_eq!(decoded.dimensions(), (5, 3));
+        assert_eq!(decoded.as_raw()[..], *gradient(5, 3).as_bytes());
+    }
+
+    #[test]
+    fn ascii_matches_binary() {
+        let img: Image = gradient(3, 2);
+        let mut ascii: Vec<u8> = Vec::new();
+        write_ppm(&img, PpmFormat::Ascii, &mut ascii).unwrap();
+        assert!(ascii.starts_with(b"P3\n3 2\n255\n0 0 255\n1 7 254\n"));
+        let mut binary: Vec<u8> = Vec::new();
+        write_ppm(&img, PpmFormat::Binary, &mut binary).unwrap();
+        // The same values, written out as decimal numbers
+        let values: Vec<u8> = String::from_utf8(ascii).unwrap().split_whitespace().skip(4).map(|v| v.parse().unwrap()).collect();
+        assert_eq!(values[..], binary[b"P6\n3 2\n255\n".len()..]);
+    }
+
+    #[test]
+    fn oversized_ppm_header_is_rejected() {
+        let error = read_ppm(&mut &b"P6 100000 100000 255\n\x00\x00\x00"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let mut out: Vec<u8> = Vec::new();
+        write_ppm(&gradient(2, 1), PpmFormat::Binary, &mut out).unwrap();
+        assert_eq!(read_ppm(&mut &out[..]).unwrap(), gradient(2, 1));
+    }
+}
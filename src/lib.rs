@@ -0,0 +1,135 @@
+#[cfg(feature = "png")]
+extern crate image as image_crate;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+
+pub mod aabb;
+pub mod axis_aligned_box;
+pub mod camera;
+pub mod cone;
+pub mod csg;
+pub mod cylinder;
+pub mod disk;
+pub mod group;
+pub mod image;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod plane;
+pub mod ray;
+pub mod render;
+pub mod rng;
+#[cfg(feature = "json")]
+pub mod scene;
+pub mod scenes;
+pub mod sphere;
+pub mod stats;
+pub mod texture;
+pub mod torus;
+pub mod transform;
+pub mod triangle;
+pub mod vector3d;
+
+pub use aabb::Aabb;
+pub use axis_aligned_box::AxisAlignedBox;
+pub use camera::{Camera, Projection};
+pub use cone::Cone;
+pub use csg::{Csg, CsgOp};
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use group::{Bvh, Group};
+#[cfg(feature = "png")]
+pub use image::{read_png, write_png};
+pub use image::{read_ppm, write_ppm, Clipping, Image, PpmFormat};
+pub use light::Light;
+pub use material::{ColorFn, Material};
+pub use matrix::Matrix;
+pub use plane::{CheckerPlane, Plane};
+pub use ray::{Hit, Ray};
+pub use render::{render, render_region, render_with_progress, render_with_stats, path_trace, Background, Integrator, RenderConfig, RenderMode, SampleMode, SamplePattern, ToneMap};
+pub use rng::{Rng, SplitMix64};
+pub use sphere::Sphere;
+pub use stats::RenderStats;
+pub use texture::Texture;
+pub use torus::Torus;
+pub use transform::{Transformed, Translated};
+pub use triangle::Triangle;
+pub use vector3d::Vector3d;
+
+/// Scenes are shared read-only between the render threads, hence `Sync`.
+/// Primitives only implement `hit`, `intersect` compares it with the
+/// nearest hit so far. Aggregates such as `Group` also override
+/// `intersect` to pass the nearest hit so far on to their children.
+pub trait Scene: Sync {
+    /// Returns the nearest hit along the ray, or `None` if it misses.
+    fn hit(&self, ray: &Ray) -> Option<Hit>;
+
+    /// Returns the nearest hit along the ray if it is closer than `i`, and
+    /// `i` itself otherwise. Tracing starts from `Hit::MISS`.
+    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
+        match self.hit(ray) {
+            Some(hit) if hit.lambda < i.lambda => hit,
+            _ => i.clone(),
+        }
+    }
+
+    /// Appends every hit along the ray to `out`, in no particular order.
+    /// By default only the nearest hit is reported.
+    fn intersect_all(&self, ray: &Ray, out: &mut Vec<Hit>) {
+        out.extend(self.hit(ray));
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool;
+    fn bounding_box(&self) -> Aabb;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_ray_yields_none() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0));
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, Material::from_color(Vector3d::ONE));
+        assert_eq!(sphere.hit(&ray), None);
+        assert_eq!(Group::new(vec![Box::new(sphere)]).hit(&ray), None);
+    }
+
+    /// Only implements `hit`, a wall facing -z at z = 5.
+    struct Wall;
+
+    impl Scene for Wall {
+        fn hit(&self, ray: &Ray) -> Option<Hit> {
+            let l: f64 = (5.0 - ray.orig.z) / ray.dir.z;
+            if l > 0.0 {
+                Some(Hit::new(l, Vector3d::new(0.0, 0.0, -1.0), Material::from_color(Vector3d::ONE)))
+            } else {
+                None
+            }
+        }
+
+        fn shadow(&self, ray: &Ray) -> bool {
+            self.hit(ray).is_some()
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            Aabb::INFINITE
+        }
+    }
+
+    #[test]
+    fn default_intersect_culls_behind_nearer_object() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 2.0), 1.0, Material::from_color(Vector3d::ZERO));
+        let group = Group::new(vec![Box::new(Wall), Box::new(sphere.clone())]);
+        assert_eq!(group.hit(&ray).unwrap().lambda, 1.0);
+        let near: Hit = sphere.hit(&ray).unwrap();
+        assert_eq!(Wall.intersect(&near, &ray), near);
+        assert_eq!(Wall.intersect(&Hit::MISS, &ray).lambda, 5.0);
+    }
+}
@@ -0,0 +1,177 @@
+use std::f64::consts;
+
+use stats;
+use vector3d::Vector3d;
+use {Hit, Material, Ray, Scene};
+
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(tag = "type", rename_all = "snake_case"))]
+pub enum Light {
+    /// Light travelling in the given (normalized) direction, without falloff.
+    Directional { direction: Vector3d, color: Vector3d },
+    /// Light emitted from `position`, attenuated by the squared distance.
+    Point { position: Vector3d, intensity: Vector3d },
+    /// Disk-shaped light that casts soft shadows. It shades like a point
+    /// light at `center`, but its visibility is estimated from `samples`
+    /// shadow rays, at least one, towards a fixed Vogel spiral of points on
+    /// the disk. The spiral is rotated by a hash of the shaded point rather
+    /// than jittered randomly, so renders are deterministic.
+    Area { center: Vector3d, normal: Vector3d, radius: f64, intensity: Vector3d, samples: u32 },
+    /// Point light shining into a cone around the (normalized) `direction`,
+    /// with a half angle of `angle` degrees. The light fades out smoothly
+    /// over the outer `falloff` degrees of the cone.
+    Spot { position: Vector3d, direction: Vector3d, angle: f64, falloff: f64, intensity: Vector3d },
+}
+
+impl Light {
+    /// Returns the direction in which the light travels to `point`, the
+    /// distance to the light and the colored intensity arriving at `point`.
+    pub fn illuminate(&self, point: Vector3d) -> (Vector3d, f64, Vector3d) {
+        match *self {
+            Light::Directional { direction, color } => (direction, f64::INFINITY, color),
+            Light::Point { position, intensity } |
+            Light::Area { center: position, intensity, .. } => {
+                let d: Vector3d = point - position;
+                let distance_squared: f64 = d.length_squared();
+                let distance: f64 = distance_squared.sqrt();
+                (d / distance, distance, intensity / distance_squared)
+            }
+            Light::Spot { position, direction, angle, falloff, intensity } => {
+                let d: Vector3d = point - position;
+                let distance_squared: f64 = d.length_squared();
+                let distance: f64 = distance_squared.sqrt();
+                let cos_outer: f64 = angle.to_radians().cos();
+                let cos_inner: f64 = (angle - falloff).max(0.0).to_radians().cos();
+                let cone: f64 = smoothstep(cos_outer, cos_inner, (d / distance).dot(direction));
+                (d / distance, distance, intensity * (cone / distance_squared))
+            }
+        }
+    }
+
+    /// Returns the fraction of the light that reaches `origin`, given the
+    /// direction and distance returned by `illuminate`.
+    pub fn visibility(&self, scene: &dyn Scene, origin: Vector3d, direction: Vector3d, distance: f64) -> f64 {
+        match *self {
+            Light::Area { center, normal, radius, samples, .. } => {
+                let (tangent, bitangent) = normal.normalize().orthonormal_basis();
+                // Points on a Vogel spiral cover the disk evenly; the spiral
+                // is rotated by a hash of the origin to jitter the pattern
+                // between neighbouring surface points.
+                let rotation: f64 = hash_unit(origin) * 2.0 * consts::PI;
+                let golden_angle: f64 = consts::PI * (3.0 - 5.0f64.sqrt());
+                let visible = (0..samples)
+                    .filter(|&k| {
+                        let r: f64 = radius * ((f64::from(k) + 0.5) / f64::from(samples)).sqrt();
+                        let (sin, cos) = (f64::from(k) * golden_angle + rotation).sin_cos();
+                        let d: Vector3d = center + (tangent * cos + bitangent * sin) * r - origin;
+                        let length: f64 = d.length();
+                        !occluded(scene, &Ray::new(origin, d / length), length)
+                    })
+                    .count();
+                visible as f64 / f64::from(samples.max(1))
+            }
+            _ => {
+                if occluded(scene, &Ray::new(origin, -direction), distance) {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// Hermite interpolation from 0 at `edge0` to 1 at `edge1`, clamped outside.
+/// Equal edges give a hard step.
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t: f64 = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Maps a point to a pseudo-random number in `[0, 1)`.
+pub(crate) fn hash_unit(v: Vector3d) -> f64 {
+    let mut h: u64 = v.x.to_bits() ^ v.y.to_bits().rotate_left(21) ^ v.z.to_bits().rotate_left(42);
+    // splitmix64 finalizer
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^= h >> 31;
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Tests whether anything blocks the shadow ray before it reaches a light
+/// at the given distance.
+pub fn occluded(scene: &dyn Scene, sray: &Ray, distance: f64) -> bool {
+    stats::shadow_ray();
+    if distance == f64::INFINITY {
+        scene.shadow(sray)
+    } else {
+        scene.intersect(&Hit::new(distance, Vector3d::ZERO, Material::default()), sray).lambda < distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AxisAlignedBox;
+
+    #[test]
+    fn penumbra_is_partly_lit() {
+        let light = Light::Area {
+            center: Vector3d::new(0.0, 10.0, 0.0),
+            normal: Vector3d::new(0.0, -1.0, 0.0),
+            radius: 2.0,
+            intensity: Vector3d::ONE,
+            samples: 64,
+        };
+        // Covers the half of the light with x < 0
+        let blocker = AxisAlignedBox::new(Vector3d::new(-100.0, 4.0, -100.0), Vector3d::new(0.0, 5.0, 100.0), Material::default());
+        let visibility = |x: f64| {
+            let point = Vector3d::new(x, 0.0, 0.0);
+            let (direction, distance, _) = light.illuminate(point);
+            light.visibility(&blocker, point, direction, distance)
+        };
+        let half: f64 = visibility(0.0);
+        assert!(half > 0.3 && half < 0.7, "{}", half);
+        assert_eq!(visibility(10.0), 1.0);
+        assert_eq!(visibility(-10.0), 0.0);
+    }
+
+    #[test]
+    fn point_light_casts_hard_shadow() {
+        let light = Light::Point { position: Vector3d::new(0.0, 10.0, 0.0), intensity: Vector3d::ONE };
+        let blocker = AxisAlignedBox::new(Vector3d::new(-100.0, 4.0, -100.0), Vector3d::new(0.0, 5.0, 100.0), Material::default());
+        for &(x, expected) in &[(0.1, 1.0), (-0.1, 0.0)] {
+            let point = Vector3d::new(x, 0.0, 0.0);
+            let (direction, distance, _) = light.illuminate(point);
+            assert_eq!(light.visibility(&blocker, point, direction, distance), expected);
+        }
+    }
+
+    #[test]
+    fn spotlight_fades_out_at_the_cone_edge() {
+        let light = Light::Spot {
+            position: Vector3d::new(0.0, 10.0, 0.0),
+            direction: Vector3d::new(0.0, -1.0, 0.0),
+            angle: 30.0,
+            falloff: 10.0,
+            intensity: Vector3d::ONE * 100.0,
+        };
+        let at = |degrees: f64| {
+            let point = Vector3d::new(10.0 * degrees.to_radians().tan(), 0.0, 0.0);
+            let (direction, distance, intensity) = light.illuminate(point);
+            assert!(direction.distance((point - Vector3d::new(0.0, 10.0, 0.0)).normalize()) < 1e-12);
+            intensity.x * distance * distance / 100.0
+        };
+        assert!((at(0.0) - 1.0).abs() < 1e-12);
+        assert!((at(15.0) - 1.0).abs() < 1e-12);
+        let edge: f64 = at(25.0);
+        assert!(edge > 0.0 && edge < 1.0, "{}", edge);
+        assert_eq!(at(31.0), 0.0);
+    }
+}
@@ -1,266 +1,242 @@
-use std::f64;
-use std::f64::INFINITY;
+extern crate raytracer;
+
+use std::borrow::Cow;
+use std::env;
+use std::f64::consts;
 use std::fs::File;
+use std::io;
+#[cfg(feature = "json")]
+use std::io::Read;
 use std::io::Write;
+use std::process;
 use std::ops::Deref;
 use std::io::BufWriter;
-use std::f64::EPSILON;
+use std::path::{Path, PathBuf};
 
-mod vector3d;
-use vector3d::Vector3d;
+use raytracer::{render_with_stats, write_ppm, Aabb, Camera, Image, Projection, RenderConfig, Scene, Vector3d};
+#[cfg(feature = "json")]
+use raytracer::scene::SceneFile;
+use raytracer::scenes::fractal;
 
-const ZERO: Vector3d = Vector3d { x: 0.0, y: 0.0, z: 0.0 };
-const ONE: Vector3d = Vector3d { x: 1.0, y: 1.0, z: 1.0 };
+#[cfg(feature = "png")]
+use raytracer::write_png;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Ray {
-    orig: Vector3d,
-    dir: Vector3d
-}
+mod cli;
 
-impl Ray {
-    pub fn new(orig: Vector3d, dir: Vector3d) -> Self {
-        Ray { orig, dir }
-    }
+#[cfg(not(feature = "png"))]
+fn write_png(_image: &Image, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "PNG output requires the png feature"))
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Hit {
-    lambda: f64,
-    normal: Vector3d,
-    color: Vector3d,
+fn default_camera(projection: Projection) -> Camera {
+    Camera::with_projection(
+        Vector3d::new(0.0, 0.0, -4.0),
+        Vector3d::new(0.0, 0.0, 1.0),
+        Vector3d::new(0.0, 1.0, 0.0),
+        projection
+    )
 }
 
-impl Hit {
-    pub fn new(lambda: f64, normal: Vector3d, color: Vector3d) -> Self {
-        Hit { lambda, normal, color }
+/// Reads the scene file, or standard input for `-`, falling back to the default camera and lights
+/// for whatever it leaves out.
+#[cfg(feature = "json")]
+fn load_scene(path: &str, config: &cli::Config, render: &mut RenderConfig) -> io::Result<(Box<dyn Scene>, Camera)> {
+    let file = if path == "-" {
+        let mut json = String::new();
+        io::stdin().read_to_string(&mut json)?;
+        SceneFile::from_json(&json)?
+    } else {
+        SceneFile::load(path)?
+    };
+    if !file.lights.is_empty() {
+        render.lights = file.lights();
     }
+    Ok((Box::new(file.build()?), file.camera.unwrap_or_else(|| default_camera(config.projection))))
 }
 
-trait Scene {
-    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit;
-    fn shadow(&self, ray: &Ray) -> bool;
-    fn bounding_box(&self) -> (Vector3d, Vector3d);
-}
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-struct Sphere {
-    center: Vector3d,
-    radius: f64,
-    color: Vector3d,
+#[cfg(not(feature = "json"))]
+fn load_scene(_path: &str, _config: &cli::Config, _render: &mut RenderConfig) -> io::Result<(Box<dyn Scene>, Camera)> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "scene files require the json feature"))
 }
 
-impl Sphere {
-    pub fn new(center: Vector3d, radius: f64, color: Vector3d) -> Self {
-        Sphere { center, radius, color }
-    }
-
-    pub fn ray_sphere(&self, ray: &Ray) -> f64 {
-        let v: Vector3d = self.center - ray.orig;
-        let b: f64 = v.dot(ray.dir);
-        let disc: f64 = b * b - v.dot(v) + self.radius * self.radius;
-        if disc < 0.0 {
-            f64::INFINITY
-        } else {
-            let d: f64 = disc.sqrt();
-            let t2: f64 = b + d;
-            if t2 < 0.0 {
-                f64::INFINITY
-            } else {
-                let t1: f64 = b - d;
-                if t1 > 0.0 {
-                    t1
-                } else {
-                    t2
-                }
+fn run(config: &cli::Config, scene: &dyn Scene, camera: &Camera, render: &RenderConfig) -> io::Result<()> {
+    let frames: u32 = match config.frames {
+        None => return render_to_file(config, scene, camera, render, Path::new(&config.output)),
+        Some(frames) => frames,
+    };
+    // Turn around the vertical axis through the center of the scene
+    let bounds: Aabb = scene.bounding_box();
+    let center: Vector3d = if bounds.is_finite() { bounds.centroid() } else { Vector3d::ZERO };
+    for frame in 0..frames {
+        eprintln!("Frame {} of {}", frame + 1, frames);
+        let angle: f64 = 2.0 * consts::PI * f64::from(frame) / f64::from(frames);
+        let camera: Camera = camera.orbit(center, Vector3d::new(0.0, 1.0, 0.0), angle);
+        render_to_file(config, scene, &camera, render, &frame_path(&config.output, frame))?;
+    }
+    Ok(())
+}
+
+/// Inserts the frame number before the extension, so that `image.ppm`
+/// becomes `image_0007.ppm`.
+fn frame_path(output: &str, frame: u32) -> PathBuf {
+    let path = Path::new(output);
+    let stem = path.file_stem().map_or(Cow::Borrowed(""), |s| s.to_string_lossy());
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{:04}.{}", stem, frame, extension.to_string_lossy()),
+        None => format!("{}_{:04}", stem, frame),
+    };
+    path.with_file_name(name)
+}
+
+/// Renders the image, reporting progress and statistics on standard error.
+fn render_image(scene: &dyn Scene, camera: &Camera, render: &RenderConfig) -> Image {
+    let mut percent = None;
+    let (image, stats) = render_with_stats(scene, camera, render, |done, total| {
+        let p = done * 100 / total;
+        if percent != Some(p) {
+            percent = Some(p);
+            eprint!("\rRendering... {:3}%", p);
+            if done == total {
+                eprintln!();
             }
         }
-    }
+    });
+    eprintln!("Rendered {}x{} in {:.2}s", image.width, image.height, stats.elapsed.as_secs_f64());
+    eprintln!("{} primary rays, {} shadow rays, {} intersection tests",
+              stats.primary_rays, stats.shadow_rays, stats.intersection_tests);
+    let clipping = image.clipping();
+    eprintln!("{} pixels clipped high, {} clipped low", clipping.clipped_high, clipping.clipped_low);
+    image
 }
 
-impl Scene for Sphere {
-    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
-        let l: f64 = self.ray_sphere(ray);
-        if l >= i.lambda {
-            *i
-        } else {
-            let n: Vector3d = ray.orig + ray.dir * l - self.center;
-            Hit::new(l, n.normalize(), self.color)
-        }
-    }
-
-    fn shadow(&self, ray: &Ray) -> bool {
-        let v: Vector3d = self.center - ray.orig;
-        let b: f64 = v.dot(ray.dir);
-        let disc: f64 = b * b - v.dot(v) + self.radius * self.radius;
-        if disc < 0.0 {
-            false
-        } else {
-            b + disc.sqrt() >= 0.0
-        }
-    }
-
-    fn bounding_box(&self) -> (Vector3d, Vector3d) {
-        let r = Vector3d::new(self.radius, self.radius, self.radius);
-        (self.center - r, self.center + r)
-    }
-}
-
-struct Group {
-    bound: Sphere,
-    objects: Vec<Box<dyn Scene>>
+/// Renders the image and writes it to `out` as a PPM in the configured
+/// format.
+fn render_to_writer<W: Write>(config: &cli::Config, scene: &dyn Scene, camera: &Camera, render: &RenderConfig, out: &mut W) -> io::Result<()> {
+    let image: Image = render_image(scene, camera, render);
+    write_ppm(&image, config.ppm_format, out)
 }
 
-impl Group {
-    pub fn new(objects: Vec<Box<dyn Scene>>, color: Vector3d) -> Self {
-        let (min, max) = Group::bounding_box(&objects);
-        let bound = Sphere::new((min + max) * 0.5, (max - min).length() * 0.5, color);
-        Group { bound, objects }
+fn render_to_file(config: &cli::Config, scene: &dyn Scene, camera: &Camera, render: &RenderConfig, path: &Path) -> io::Result<()> {
+    if path == Path::new("-") {
+        let stdout = io::stdout();
+        let mut out = BufWriter::new(stdout.lock());
+        render_to_writer(config, scene, camera, render, &mut out)?;
+        return out.flush();
     }
-
-    fn bounding_box(objects: &[Box<dyn Scene>]) -> (Vector3d, Vector3d) {
-        let mut min = Vector3d::new(f64::MAX, f64::MAX, f64::MAX);
-        let mut max = Vector3d::new(f64::MIN, f64::MIN, f64::MIN);
-        for scene in objects {
-            let (mi, ma) = scene.bounding_box();
-            min = min.min(mi);
-            max = max.max(ma);
-        }
-        (min, max)
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) {
+        return write_png(&render_image(scene, camera, render), path);
     }
+    let mut file = BufWriter::new(File::create(path)?);
+    render_to_writer(config, scene, camera, render, &mut file)?;
+    file.flush()
 }
 
-impl Scene for Group {
-    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
-        let l: f64 = self.bound.ray_sphere(ray);
-        if l >= i.lambda {
-            *i
-        } else {
-            let mut out: Hit = *i;
-            for scene in &self.objects {
-                out = scene.intersect(&out, ray);
-            }
-            out
+fn main() {
+    let program = env::args().next().unwrap_or_else(|| String::from("raytracer"));
+    let config = match cli::parse_args(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", cli::usage(&program));
+            process::exit(2);
         }
-    }
-
-    fn shadow(&self, ray: &Ray) -> bool {
-        if self.bound.shadow(ray) {
-            for scene in &self.objects {
-                if scene.shadow(ray) {
-                    return true;
-                }
+    };
+    let mut render = config.render.clone();
+    let (scene, camera) = match config.scene {
+        Some(ref path) => match load_scene(path, &config, &mut render) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Failed reading {}: {}", path, e);
+                process::exit(1);
             }
-        }
-        false
-    }
-
-    fn bounding_box(&self) -> (Vector3d, Vector3d) {
-        Group::bounding_box(&self.objects)
-    }
-}
-
-const MAX_NESTING: i32 = 1;
-
-fn do_ray_trace(lights: &[Vector3d], ray: Ray, scene: &dyn Scene, nesting: i32, hit: Hit, light: &Vector3d) -> Vector3d {
-    let g: f64 = hit.normal.dot(*light);
-    if g >= 0.0 {
-        return ZERO;
-    }
-
-    let origin: Vector3d = ray.orig + 
-        ray.dir * hit.lambda + 
-        hit.normal * EPSILON.sqrt();
-    let sray = Ray::new(origin, -*light);
-    let color = if scene.shadow(&sray) {
-        ZERO
-    } else {
-        -g * hit.color
+        },
+        None => (fractal(config.level, Vector3d::new(0.0, -1.0, 0.0), 1.0), default_camera(config.projection)),
     };
-    let reflection_color = if nesting < MAX_NESTING {
-        let dir = ray.dir - (2.0 * hit.normal.dot(ray.dir)) * hit.normal;
-        let reflection = Ray::new(origin, dir);
-        0.5 * ray_trace(lights, reflection, scene, nesting + 1)
+    let camera = if config.aperture > 0.0 {
+        camera.with_depth_of_field(config.aperture, config.focus_distance)
     } else {
-        ZERO
+        camera
     };
-    1.0 - (1.0 - color) * (1.0 - reflection_color)
-}
-
-fn ray_trace(lights: &[Vector3d], ray: Ray, scene: &dyn Scene, nesting: i32) -> Vector3d {
-    let hit: Hit = scene.intersect(&Hit::new(INFINITY, ZERO, ZERO), &ray);
-    if hit.lambda == INFINITY {
-        return ZERO;
+    if let Err(e) = run(&config, scene.deref(), &camera, &render) {
+        eprintln!("Failed writing {}: {}", config.output, e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use raytracer::{render, PpmFormat};
+
+    fn small_config() -> cli::Config {
+        cli::parse_args(["--width", "8", "--height", "6", "--samples", "1", "--level", "2"].iter().map(|s| s.to_string())).unwrap()
+    }
+
+    fn render_small(path: &Path) -> io::Result<()> {
+        let mut config = small_config();
+        config.output = path.to_string_lossy().into_owned();
+        let scene = fractal(config.level, Vector3d::new(0.0, -1.0, 0.0), 1.0);
+        run(&config, scene.deref(), &default_camera(config.projection), &config.render)
+    }
+
+    #[test]
+    fn writes_ppm_to_path() {
+        let path: PathBuf = env::temp_dir().join(format!("raytracer-test-{}.ppm", process::id()));
+        render_small(&path).unwrap();
+        let bytes: Vec<u8> = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(bytes.starts_with(b"P6\n8 6\n255\n"), "{:?}", &bytes[..12]);
+        assert_eq!(bytes.len(), 11 + 8 * 6 * 3);
+    }
+
+    #[test]
+    fn unwritable_path_returns_error() {
+        let path: PathBuf = env::temp_dir().join(format!("raytracer-missing-{}", process::id())).join("image.ppm");
+        let error = render_small(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn frame_number_goes_before_extension() {
+        assert_eq!(frame_path("out/image.ppm", 7), PathBuf::from("out/image_0007.ppm"));
+        assert_eq!(frame_path("image", 12), PathBuf::from("image_0012"));
+    }
+
+    #[test]
+    fn renders_one_file_per_frame() {
+        let mut config = small_config();
+        config.frames = Some(2);
+        config.output = env::temp_dir().join(format!("raytracer-frames-{}.ppm", process::id())).to_string_lossy().into_owned();
+        let scene = fractal(config.level, Vector3d::new(0.0, -1.0, 0.0), 1.0);
+        run(&config, scene.as_ref(), &default_camera(config.projection), &config.render).unwrap();
+        let frames: Vec<Vec<u8>> = (0..2)
+            .map(|frame| {
+                let path: PathBuf = frame_path(&config.output, frame);
+                let bytes: Vec<u8> = fs::read(&path).unwrap();
+                fs::remove_file(&path).unwrap();
+                bytes
+            })
+            .collect();
+        assert!(frames[0].starts_with(b"P6\n8 6\n255\n") && frames[1].starts_with(b"P6\n8 6\n255\n"));
+        assert!(frames[0] != frames[1]);
+    }
+
+    #[test]
+    fn renders_into_a_buffer() {
+        let config = small_config();
+        let scene = fractal(config.level, Vector3d::new(0.0, -1.0, 0.0), 1.0);
+        let camera = default_camera(config.projection);
+        let mut out: Vec<u8> = Vec::new();
+        render_to_writer(&config, scene.as_ref(), &camera, &config.render, &mut out).unwrap();
+        let header: &[u8] = b"P6\n8 6\n255\n";
+        assert!(out.starts_with(header));
+        assert_eq!(&out[header.len()..], render(scene.as_ref(), &camera, &config.render).as_bytes());
+
+        let ascii = cli::Config { ppm_format: PpmFormat::Ascii, ..small_config() };
+        let mut out: Vec<u8> = Vec::new();
+        render_to_writer(&ascii, scene.as_ref(), &camera, &ascii.render, &mut out).unwrap();
+        assert!(out.starts_with(b"P3\n8 6\n255\n"));
     }
-    1.0 - lights.iter()
-        .map(|light| {
-            do_ray_trace(lights, ray, scene, nesting, hit, light)
-        })
-        .fold(ONE, |a, b| { a * (1.0 - b) })
-}
-
-fn create(level: i32, c: Vector3d, r: f64) -> Box<dyn Scene> {
-    let sphere: Sphere = Sphere::new(c, r, c.abs().normalize());
-    if level == 1 {
-        return Box::new(sphere);
-    }
-    let mut objects: Vec<Box<dyn Scene>> = Vec::new();
-    objects.push(Box::new(sphere));
-    let rn: f64 = 3.0 * r / 12.0f64.sqrt();
-    let mut dz: i32 = -1;
-    while dz <= 1 {
-        let mut dx: i32 = -1;
-        while dx <= 1 {
-            let c2: Vector3d = c + Vector3d::new(f64::from(dx), 1.0, f64::from(dz)) * (rn);
-            objects.push(create(level - 1, c2, r * 0.5));
-            dx += 2;
-        }
-        dz += 2;
-    }
-    Box::new(Group::new(objects, ZERO))
-}
-
-fn run(n: i32, level: i32, ss: i32) {
-    let color_scale: f64 = 255.0 / (f64::from(ss) * f64::from(ss));
-    let lights = vec![
-        Vector3d::new(-1.0, -3.0, 2.0).normalize(),
-        Vector3d::new(3.0, -1.0, 2.0).normalize(),
-    ];
-    let orig = Vector3d::new(0.0, 0.0, -4.0);
-    let scene: Box<dyn Scene> = create(level, Vector3d::new(0.0, -1.0, 0.0), 1.0);
-    let mut file = BufWriter::new(File::create("image.ppm")
-                                  .expect("Failed to create image.ppm"));
-
-    file.write_all(format!("P6\n{} {}\n255\n", n, n).as_bytes())
-        .expect("Failed writing header to image.ppm");
-    for y in (0..n).rev() {
-        for x in 0..n {
-            let mut g: Vector3d = ZERO;
-            for dx in 0..ss {
-                for dy in 0..ss {
-                    let d: Vector3d = Vector3d::new(
-                        f64::from(x) + f64::from(dx) / f64::from(ss) - f64::from(n) * 0.5,
-                        f64::from(y) + f64::from(dy) / f64::from(ss) - f64::from(n) * 0.5,
-                        f64::from(n)
-                    );
-                    let ray: Ray = Ray::new(
-                        orig,
-                        d.normalize()
-                    );
-                    g += ray_trace(
-                        &lights,
-                        ray,
-                        scene.deref(),
-                        0);
-                }
-            }
-            let c: Vector3d = Vector3d::new(0.5, 0.5, 0.5) + g * color_scale;
-            file.write_all(&[c.x as u8, c.y as u8, c.z as u8])
-                .expect("Failed writing byte to image.ppm");
-        }
-    }
-}
-
-fn main() {
-    run(512, 9, 4);
 }
@@ -0,0 +1,110 @@
+use std::ptr;
+use std::sync::Arc;
+
+use vector3d::Vector3d;
+
+#[cfg(feature = "serde")]
+use serde::de::Error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+
+use texture::Texture;
+
+/// A procedural color. Two of them are equal when they point to the same
+/// function.
+#[derive(Debug, Copy, Clone)]
+pub struct ColorFn(pub fn(point: Vector3d) -> Vector3d);
+
+impl PartialEq for ColorFn {
+    fn eq(&self, other: &ColorFn) -> bool {
+        ptr::fn_addr_eq(self.0, other.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize), serde(default))]
+pub struct Material {
+    pub color: Vector3d,
+    pub reflectivity: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: f64,
+    pub transparency: f64,
+    pub ior: f64,
+    /// Light given off by the surface itself. The path tracer uses emissive
+    /// surfaces as light sources.
+    pub emission: Vector3d,
+    /// Procedural color evaluated at the hit point, replacing `color`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub color_fn: Option<ColorFn>,
+    /// Image replacing `color` on surfaces with texture coordinates. Scene
+    /// files give the path of the image.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "load_texture"))]
+    pub texture: Option<Arc<Texture>>,
+}
+
+#[cfg(feature = "serde")]
+fn load_texture<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Arc<Texture>>, D::Error> {
+    let path: String = String::deserialize(deserializer)?;
+    let texture: Texture = Texture::load(&path).map_err(|e| D::Error::custom(format!("{}: {}", path, e)))?;
+    Ok(Some(Arc::new(texture)))
+}
+
+impl Material {
+    pub const fn new(color: Vector3d, reflectivity: f64, diffuse: f64) -> Self {
+        Material { color, reflectivity, diffuse, specular: 0.0, shininess: 0.0, transparency: 0.0, ior: 1.0, emission: Vector3d::ZERO, color_fn: None, texture: None }
+    }
+
+    /// Adds a Phong highlight of the given strength and exponent.
+    pub fn with_specular(self, specular: f64, shininess: f64) -> Self {
+        Material { specular, shininess, ..self }
+    }
+
+    /// Makes the material let through the given fraction of light, refracted
+    /// with index of refraction `ior`.
+    pub fn with_transparency(self, transparency: f64, ior: f64) -> Self {
+        Material { transparency, ior, ..self }
+    }
+
+    pub fn with_emission(self, emission: Vector3d) -> Self {
+        Material { emission, ..self }
+    }
+
+    /// Colors the surface with a function of the hit point, for stripes,
+    /// gradients and other solid textures.
+    pub fn with_color_fn(self, color_fn: fn(point: Vector3d) -> Vector3d) -> Self {
+        Material { color_fn: Some(ColorFn(color_fn)), ..self }
+    }
+
+    /// Wraps the texture around the surface by its texture coordinates. The
+    /// texture is shared between the clones of the material.
+    pub fn with_texture(self, texture: Arc<Texture>) -> Self {
+        Material { texture: Some(texture), ..self }
+    }
+
+    /// Returns the color at the hit point and texture coordinates, from the
+    /// texture, the procedural color or the plain color, in that order.
+    pub fn color_at(&self, point: Vector3d, uv: Option<(f64, f64)>) -> Vector3d {
+        match (&self.texture, uv, self.color_fn) {
+            (Some(texture), Some((u, v)), _) => texture.sample(u, v),
+            (_, _, Some(ColorFn(color_fn))) => color_fn(point),
+            _ => self.color,
+        }
+    }
+
+    pub const fn from_color(color: Vector3d) -> Self {
+        Material::new(color, 0.5, 1.0)
+    }
+
+    /// A perfect mirror: it shows only the reflected scene, without diffuse
+    /// light of its own.
+    pub const fn mirror() -> Self {
+        Material::new(Vector3d::ONE, 1.0, 0.0)
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::from_color(Vector3d::ZERO)
+    }
+}
@@ -0,0 +1,94 @@
+use std::ops::Mul;
+
+use vector3d::Vector3d;
+
+/// A 3×3 matrix, stored as its rows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: [Vector3d; 3],
+}
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix {
+        rows: [
+            Vector3d { x: 1.0, y: 0.0, z: 0.0 },
+            Vector3d { x: 0.0, y: 1.0, z: 0.0 },
+            Vector3d { x: 0.0, y: 0.0, z: 1.0 },
+        ],
+    };
+
+    pub fn new(rows: [Vector3d; 3]) -> Self {
+        Matrix { rows }
+    }
+
+    pub fn from_columns(columns: [Vector3d; 3]) -> Self {
+        Matrix::new(columns).transpose()
+    }
+
+    pub fn scale(s: Vector3d) -> Self {
+        Matrix::new([
+            Vector3d::new(s.x, 0.0, 0.0),
+            Vector3d::new(0.0, s.y, 0.0),
+            Vector3d::new(0.0, 0.0, s.z),
+        ])
+    }
+
+    /// Rotation around the (normalized) `axis`, see
+    /// `Vector3d::rotate_around_axis`.
+    pub fn rotation(axis: Vector3d, angle_radians: f64) -> Self {
+        let [x, y, z] = Matrix::IDENTITY.rows;
+        Matrix::from_columns([
+            x.rotate_around_axis(axis, angle_radians),
+            y.rotate_around_axis(axis, angle_radians),
+            z.rotate_around_axis(axis, angle_radians),
+        ])
+    }
+
+    pub fn transpose(self) -> Matrix {
+        let [a, b, c] = self.rows;
+        Matrix::new([
+            Vector3d::new(a.x, b.x, c.x),
+            Vector3d::new(a.y, b.y, c.y),
+            Vector3d::new(a.z, b.z, c.z),
+        ])
+    }
+
+    pub fn determinant(self) -> f64 {
+        let [a, b, c] = self.rows;
+        a.dot(b.cross(c))
+    }
+
+    /// Returns `None` for a singular matrix.
+    pub fn inverse(self) -> Option<Matrix> {
+        let [a, b, c] = self.rows;
+        let det: f64 = self.determinant();
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        // The columns of the adjugate are the cross products of the rows.
+        Some(Matrix::from_columns([b.cross(c) / det, c.cross(a) / det, a.cross(b) / det]))
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Matrix {
+        Matrix::IDENTITY
+    }
+}
+
+impl Mul<Vector3d> for Matrix {
+    type Output = Vector3d;
+
+    fn mul(self, v: Vector3d) -> Vector3d {
+        Vector3d::new(self.rows[0].dot(v), self.rows[1].dot(v), self.rows[2].dot(v))
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, other: Matrix) -> Matrix {
+        let columns = other.transpose().rows;
+        Matrix::new(self.rows.map(|row| Vector3d::new(row.dot(columns[0]), row.dot(columns[1]), row.dot(columns[2]))))
+    }
+}
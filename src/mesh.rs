@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use vector3d::Vector3d;
+use {Group, Material, Scene, Triangle};
+
+/// Loads a Wavefront OBJ file as a group of triangles. Only vertex (`v`) and
+/// face (`f`) statements are used; faces with more than three vertices are
+/// triangulated as a fan.
+pub fn load_obj(path: &str) -> io::Result<Group> {
+    parse_obj(BufReader::new(File::open(path)?))
+}
+
+pub fn parse_obj<R: BufRead>(reader: R) -> io::Result<Group> {
+    let material = Material::from_color(Vector3d::ONE);
+    let mut vertices: Vec<Vector3d> = Vec::new();
+    let mut objects: Vec<Box<dyn Scene>> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut coords = [0.0; 3];
+                for c in &mut coords {
+                    *c = parse_number(tokens.next(), &line)?;
+                }
+                vertices.push(Vector3d::from(coords));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|t| vertex_index(t, vertices.len()))
+                    .collect::<io::Result<Vec<usize>>>()?;
+                if face.len() < 3 {
+                    return Err(invalid_data(&line));
+                }
+                for k in 1..face.len() - 1 {
+                    objects.push(Box::new(Triangle::new(
+                        vertices[face[0]],
+                        vertices[face[k]],
+                        vertices[face[k + 1]],
+                        material.clone())));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Group::new(objects))
+}
+
+fn parse_number(token: Option<&str>, line: &str) -> io::Result<f64> {
+    token
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| invalid_data(line))
+}
+
+/// Resolves a face vertex reference such as `3`, `3/1/2` or `-1` to a
+/// zero-based index into the vertices read so far.
+fn vertex_index(token: &str, count: usize) -> io::Result<usize> {
+    let index: i64 = token
+        .split('/')
+        .next()
+        .and_then(|t| t.parse().ok())
+        .ok_or_else(|| invalid_data(token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        Err(invalid_data(token))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+fn invalid_data(context: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Invalid OBJ data: {}", context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Aabb, Hit, Ray};
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    const QUAD: &str = "# a unit quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 -1\nf 1/1/1 2/2/1 3/3/1 4/4/1\n";
+
+    #[test]
+    fn quad_becomes_two_triangles() {
+        let group: Group = parse_obj(QUAD.as_bytes()).unwrap();
+        assert_eq!(group.bounding_box(), Aabb::new(Vector3d::ZERO, Vector3d::new(1.0, 1.0, 0.0)));
+        // Each half of the quad is covered by one of the triangles
+        for &(x, y) in &[(0.8, 0.2), (0.2, 0.8)] {
+            let ray = Ray::new(Vector3d::new(x, y, -1.0), Vector3d::new(0.0, 0.0, 1.0));
+            assert_eq!(group.intersect(&miss(), &ray).lambda, 1.0, "({}, {})", x, y);
+        }
+        let outside = Ray::new(Vector3d::new(1.2, 0.5, -1.0), Vector3d::new(0.0, 0.0, 1.0));
+        assert_eq!(group.intersect(&miss(), &outside), miss());
+    }
+
+    #[test]
+    fn bad_faces_are_rejected() {
+        for obj in &["v 0 0 0\nf 1 2 3\n", "v 0 0 0\nv 1 0 0\nf 1 2\n", "v 0 x 0\n", "v 0 0 0\nf 0 1 1\n"] {
+            let error = parse_obj(obj.as_bytes()).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", obj);
+        }
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n".as_bytes()).is_ok());
+    }
+}
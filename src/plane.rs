@@ -0,0 +1,159 @@
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plane {
+    point: Vector3d,
+    normal: Vector3d,
+    material: Material,
+}
+
+impl Plane {
+    pub fn new(point: Vector3d, normal: Vector3d, material: Material) -> Self {
+        Plane { point, normal: normal.normalize(), material }
+    }
+
+    pub fn ray_plane(&self, ray: &Ray) -> f64 {
+        let denom: f64 = ray.dir.dot(self.normal);
+        if denom.abs() < f64::EPSILON {
+            return f64::INFINITY;
+        }
+        let t: f64 = (self.point - ray.orig).dot(self.normal) / denom;
+        if t > 0.0 {
+            t
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+impl Scene for Plane {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_plane(ray);
+        if l == f64::INFINITY {
+            None
+        } else if ray.dir.dot(self.normal) > 0.0 {
+            Some(Hit::new(l, -self.normal, self.material.clone()))
+        } else {
+            Some(Hit::new(l, self.normal, self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_plane(ray) < f64::INFINITY
+    }
+
+    /// A plane is unbounded, so its box spans all of space. Every ray passes
+    /// the slab test of `Group` against such a box, which leaves the plane
+    /// itself to decide.
+    fn bounding_box(&self) -> Aabb {
+        Aabb::INFINITE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckerPlane {
+    plane: Plane,
+    other: Material,
+    cell_size: f64,
+}
+
+impl CheckerPlane {
+    pub fn new(point: Vector3d, normal: Vector3d, material_a: Material, material_b: Material, cell_size: f64) -> Self {
+        CheckerPlane { plane: Plane::new(point, normal, material_a), other: material_b, cell_size }
+    }
+
+    /// Picks the material of the checker cell containing `point`, based on
+    /// its world `x` and `z` coordinates.
+    pub fn material_at(&self, point: Vector3d) -> Material {
+        let cell_x = (point.x / self.cell_size).floor() as i64;
+        let cell_z = (point.z / self.cell_size).floor() as i64;
+        if (cell_x + cell_z).rem_euclid(2) == 0 {
+            self.plane.material.clone()
+        } else {
+            self.other.clone()
+        }
+    }
+}
+
+impl Scene for CheckerPlane {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        let hit: Hit = self.plane.hit(ray)?;
+        Some(Hit::new(hit.lambda, hit.normal, self.material_at(ray.orig + ray.dir * hit.lambda)))
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        self.plane.shadow(ray)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.plane.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn ground() -> Plane {
+        Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_hits_ground_plane() {
+        let ray = Ray::new(Vector3d::new(1.0, 2.0, 3.0), Vector3d::new(0.0, -1.0, 1.0).normalize());
+        let hit: Hit = ground().intersect(&miss(), &ray);
+        assert!((hit.lambda - 2.0f64.sqrt() * 2.0).abs() < 1e-12);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 1.0, 0.0));
+        assert!(ground().shadow(&ray));
+        // From below, the normal faces the ray
+        let below = Ray::new(Vector3d::new(0.0, -1.0, 0.0), Vector3d::new(0.0, 1.0, 0.0));
+        assert_eq!(ground().intersect(&miss(), &below).normal, Vector3d::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn ray_misses_ground_plane() {
+        let up = Ray::new(Vector3d::new(0.0, 1.0, 0.0), Vector3d::new(0.0, 1.0, 1.0).normalize());
+        assert_eq!(ground().intersect(&miss(), &up), miss());
+        assert!(!ground().shadow(&up));
+        let parallel = Ray::new(Vector3d::new(0.0, 1.0, 0.0), Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(ground().intersect(&miss(), &parallel), miss());
+        // A nearer hit so far wins
+        let down = Ray::new(Vector3d::new(0.0, 1.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        let near = Hit::new(0.5, Vector3d::new(0.0, 1.0, 0.0), Material::default());
+        assert_eq!(ground().intersect(&near, &down), near);
+        assert_eq!(ground().bounding_box(), Aabb::INFINITE);
+    }
+
+    fn checker(cell_size: f64) -> CheckerPlane {
+        CheckerPlane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), Material::from_color(Vector3d::ONE),
+                          Material::from_color(Vector3d::ZERO), cell_size)
+    }
+
+    #[test]
+    fn adjacent_cells_differ() {
+        let color = |x: f64, z: f64| checker(1.0).material_at(Vector3d::new(x, 0.0, z)).color;
+        assert_eq!(color(0.5, 0.5), Vector3d::ONE);
+        assert_eq!(color(1.5, 0.5), Vector3d::ZERO);
+        assert_eq!(color(0.5, 1.5), Vector3d::ZERO);
+        assert_eq!(color(1.5, 1.5), Vector3d::ONE);
+        assert_eq!(color(-0.5, 0.5), Vector3d::ZERO);
+        assert_eq!(color(-0.5, -0.5), Vector3d::ONE);
+        assert_eq!(checker(2.0).material_at(Vector3d::new(1.5, 0.0, 0.5)).color, Vector3d::ONE);
+    }
+
+    #[test]
+    fn hit_takes_material_of_cell() {
+        let ray = Ray::new(Vector3d::new(1.5, 1.0, 0.5), Vector3d::new(0.0, -1.0, 0.0));
+        let hit: Hit = checker(1.0).intersect(&miss(), &ray);
+        assert_eq!(hit.lambda, 1.0);
+        assert_eq!(hit.material.color, Vector3d::ZERO);
+    }
+}
@@ -0,0 +1,49 @@
+use vector3d::Vector3d;
+use Material;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub orig: Vector3d,
+    pub dir: Vector3d
+}
+
+impl Ray {
+    pub fn new(orig: Vector3d, dir: Vector3d) -> Self {
+        Ray { orig, dir }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub lambda: f64,
+    pub normal: Vector3d,
+    pub material: Material,
+    /// Weights of the corners of the triangle that was hit, for triangles
+    /// only.
+    pub barycentric: Option<[f64; 3]>,
+    /// Texture coordinates of the hit, for textured spheres only.
+    pub uv: Option<(f64, f64)>,
+}
+
+impl Hit {
+    /// The hit that `Scene::intersect` starts from: infinitely far away, so
+    /// that any actual intersection is closer.
+    pub const MISS: Hit = Hit { lambda: f64::INFINITY, normal: Vector3d::ZERO, material: Material::from_color(Vector3d::ZERO), barycentric: None, uv: None };
+
+    pub fn new(lambda: f64, normal: Vector3d, material: Material) -> Self {
+        Hit { lambda, normal, material, barycentric: None, uv: None }
+    }
+
+    pub fn with_barycentric(self, barycentric: [f64; 3]) -> Self {
+        Hit { barycentric: Some(barycentric), ..self }
+    }
+
+    pub fn with_uv(self, u: f64, v: f64) -> Self {
+        Hit { uv: Some((u, v)), ..self }
+    }
+
+    /// Returns whether no object was hit, i.e. the hit is still at infinity.
+    pub fn is_miss(&self) -> bool {
+        self.lambda == f64::INFINITY
+    }
+}
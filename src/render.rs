@@ -0,0 +1,1617 @@
+use std::cell::Cell;
+#[cfg(not(feature = "rayon"))]
+use std::panic;
+use std::sync::Mutex;
+#[cfg(not(feature = "rayon"))]
+use std::thread;
+use std::time::Instant;
+
+use camera::Camera;
+use image::Image;
+use light::Light;
+use rng::{Rng, SplitMix64};
+use stats;
+use stats::RenderStats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Ray, Scene};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Background {
+    Solid(Vector3d),
+    /// Vertical gradient from `bottom` (looking straight down) to `top`
+    /// (looking straight up).
+    Gradient { bottom: Vector3d, top: Vector3d },
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Vector3d {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { bottom, top } => bottom.lerp(top, 0.5 * (ray.dir.y + 1.0)),
+        }
+    }
+}
+
+/// How the primary rays of a pixel are distributed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SampleMode {
+    /// A regular `n`×`n` grid of samples per pixel.
+    Uniform(u32),
+    /// An `n`×`n` grid with every sample placed at a random position in its
+    /// cell. The same `seed` always gives the same image.
+    Jittered { samples: u32, seed: u64 },
+    /// `samples`×`samples` samples per pixel, placed by `pattern`.
+    Pattern { pattern: SamplePattern, samples: u32 },
+    /// Samples the four pixel corners and splits the pixel into quadrants,
+    /// up to `max_depth` times, while the corner colors vary by more than
+    /// `threshold`.
+    Adaptive { max_depth: u32, threshold: f64 },
+}
+
+/// Placement of the samples within a pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SamplePattern {
+    /// A regular grid, the same as `SampleMode::Uniform`.
+    Grid,
+    /// A grid rotated so that no two samples share a row or column, which
+    /// smooths nearly horizontal and vertical edges.
+    RotatedGrid,
+    /// The Halton sequence with the given bases for x and y, which should be
+    /// coprime and at least 2, e.g. 2 and 3.
+    Halton(u32, u32),
+}
+
+impl SamplePattern {
+    /// Returns the offsets in `[0, 1)²` of `n`×`n` samples within a pixel.
+    ///
+    /// Panics if a Halton base is below 2.
+    pub fn offsets(self, n: u32) -> Vec<(f64, f64)> {
+        let cell: f64 = 1.0 / f64::from(n);
+        let grid = (0..n).flat_map(move |i| (0..n).map(move |j| (f64::from(i), f64::from(j))));
+        match self {
+            SamplePattern::Grid => grid.map(|(i, j)| (i * cell, j * cell)).collect(),
+            SamplePattern::RotatedGrid => grid
+                .map(|(i, j)| ((i + (j + 0.5) * cell) * cell, (j + (f64::from(n) - i - 0.5) * cell) * cell))
+                .collect(),
+            SamplePattern::Halton(base_x, base_y) => {
+                assert!(base_x >= 2 && base_y >= 2, "Halton bases must be at least 2");
+                (1..=n * n).map(|k| (radical_inverse(k, base_x), radical_inverse(k, base_y))).collect()
+            }
+        }
+    }
+}
+
+/// Mirrors the digits of `k` in the given base around the radix point.
+fn radical_inverse(mut k: u32, base: u32) -> f64 {
+    let inv_base: f64 = 1.0 / f64::from(base);
+    let mut scale: f64 = inv_base;
+    let mut result: f64 = 0.0;
+    while k > 0 {
+        result += f64::from(k % base) * scale;
+        k /= base;
+        scale *= inv_base;
+    }
+    result
+}
+
+/// What the rendered image shows. The debug modes other than `Wireframe`
+/// skip lighting and gamma correction, and show rays that miss as black.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Shaded,
+    /// Distance along the primary ray, from white at the camera to black at
+    /// `far` and beyond.
+    Depth { far: f64 },
+    /// The surface normal mapped to RGB as `0.5 * n + 0.5`.
+    Normal,
+    /// Shaded by `ray_trace`, with the parts of triangles within `width` of an edge, in
+    /// barycentric coordinates, drawn in `color`.
+    Wireframe { width: f64, color: Vector3d },
+}
+
+impl RenderMode {
+    /// Returns whether the mode shows the lit scene.
+    pub fn is_shaded(self) -> bool {
+        matches!(self, RenderMode::Shaded | RenderMode::Wireframe { .. })
+    }
+}
+
+/// The light transport algorithm used for shaded renders.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Direct light from the `lights` plus mirror reflection and refraction,
+    /// see `ray_trace`.
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing with diffuse interreflection, averaging
+    /// `samples_per_pixel` paths for every primary sample, see `path_trace`.
+    /// The same `seed` always gives the same image.
+    PathTrace { samples_per_pixel: u32, seed: u64 },
+}
+
+/// Compression of bright linear colors into the displayable range, applied
+/// before gamma correction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ToneMap {
+    /// Colors above 1 are clipped.
+    None,
+    /// Maps each channel `c` to `c / (1 + c)`, keeping detail in highlights.
+    Reinhard,
+}
+
+impl ToneMap {
+    pub fn apply(self, color: Vector3d) -> Vector3d {
+        match self {
+            ToneMap::None => color,
+            ToneMap::Reinhard => {
+                let c: Vector3d = color.max(Vector3d::ZERO);
+                c / (Vector3d::ONE + c)
+            }
+        }
+    }
+}
+
+/// Everything besides the geometry and the camera that determines the
+/// rendered image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    pub width: u32,
+    pub height: u32,
+    pub sampling: SampleMode,
+    pub mode: RenderMode,
+    pub integrator: Integrator,
+    /// Maximum number of reflection and refraction bounces, or of path
+    /// tracing bounces.
+    pub max_depth: i32,
+    /// Multiplier of the averaged linear color, applied before tone mapping.
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub gamma: f64,
+    pub background: Background,
+    pub ambient: Vector3d,
+    /// Weight of one diffusely bounced ray per hit in Whitted shading, which
+    /// lets diffuse surfaces light each other. 0 disables it.
+    pub indirect: f64,
+    /// Exponential distance fog: a hit at distance `d` is blended towards
+    /// `fog_color` by `1 - exp(-fog_density * d)`. A density of 0 disables
+    /// it.
+    pub fog_density: f64,
+    pub fog_color: Vector3d,
+    pub lights: Vec<Light>,
+    /// Offset of shadow, reflection and refraction rays from the surface,
+    /// relative to the size of the coordinates involved.
+    pub shadow_bias: f64,
+    /// Number of worker threads, 0 uses all available cores.
+    pub threads: usize,
+    /// Edge length of the square tiles that the worker threads take from a
+    /// shared queue, 0 assigns whole rows to the threads up front instead.
+    /// Defaults to 0, so that progress is reported once per row.
+    pub tile_size: u32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            width: 512,
+            height: 512,
+            sampling: SampleMode::Uniform(4),
+            mode: RenderMode::Shaded,
+            integrator: Integrator::Whitted,
+            max_depth: 1,
+            exposure: 1.0,
+            tone_map: ToneMap::None,
+            gamma: 2.2,
+            background: Background::Solid(Vector3d::ZERO),
+            ambient: Vector3d::ZERO,
+            indirect: 0.0,
+            fog_density: 0.0,
+            fog_color: Vector3d::ZERO,
+            lights: vec![
+                Light::Directional {
+                    direction: Vector3d::new(-1.0, -3.0, 2.0).normalize(),
+                    color: Vector3d::ONE,
+                },
+                Light::Directional {
+                    direction: Vector3d::new(3.0, -1.0, 2.0).normalize(),
+                    color: Vector3d::ONE,
+                },
+            ],
+            shadow_bias: 1e-9,
+            threads: 0,
+            tile_size: 0,
+        }
+    }
+}
+
+/// Builder methods, so that a configuration can be written as
+/// `RenderConfig::default().width(256).samples(2).build()`.
+impl RenderConfig {
+    pub fn width(self, width: u32) -> Self {
+        RenderConfig { width, ..self }
+    }
+
+    pub fn height(self, height: u32) -> Self {
+        RenderConfig { height, ..self }
+    }
+
+    /// Supersamples a uniform grid of `samples` × `samples` per pixel.
+    pub fn samples(self, samples: u32) -> Self {
+        RenderConfig { sampling: SampleMode::Uniform(samples), ..self }
+    }
+
+    pub fn sampling(self, sampling: SampleMode) -> Self {
+        RenderConfig { sampling, ..self }
+    }
+
+    pub fn mode(self, mode: RenderMode) -> Self {
+        RenderConfig { mode, ..self }
+    }
+
+    pub fn integrator(self, integrator: Integrator) -> Self {
+        RenderConfig { integrator, ..self }
+    }
+
+    pub fn max_depth(self, max_depth: i32) -> Self {
+        RenderConfig { max_depth, ..self }
+    }
+
+    pub fn exposure(self, exposure: f64) -> Self {
+        RenderConfig { exposure, ..self }
+    }
+
+    pub fn tone_map(self, tone_map: ToneMap) -> Self {
+        RenderConfig { tone_map, ..self }
+    }
+
+    pub fn gamma(self, gamma: f64) -> Self {
+        RenderConfig { gamma, ..self }
+    }
+
+    pub fn background(self, background: Background) -> Self {
+        RenderConfig { background, ..self }
+    }
+
+    pub fn ambient(self, ambient: Vector3d) -> Self {
+        RenderConfig { ambient, ..self }
+    }
+
+    pub fn indirect(self, indirect: f64) -> Self {
+        RenderConfig { indirect, ..self }
+    }
+
+    /// Adds fog of the given density and color.
+    pub fn fog(self, fog_density: f64, fog_color: Vector3d) -> Self {
+        RenderConfig { fog_density, fog_color, ..self }
+    }
+
+    pub fn lights(self, lights: Vec<Light>) -> Self {
+        RenderConfig { lights, ..self }
+    }
+
+    pub fn shadow_bias(self, shadow_bias: f64) -> Self {
+        RenderConfig { shadow_bias, ..self }
+    }
+
+    pub fn threads(self, threads: usize) -> Self {
+        RenderConfig { threads, ..self }
+    }
+
+    pub fn tile_size(self, tile_size: u32) -> Self {
+        RenderConfig { tile_size, ..self }
+    }
+
+    /// Returns the configuration if the values make sense: a nonempty image,
+    /// at least one sample, and no negative depth, fog, indirect weight,
+    /// bias or exposure.
+    pub fn build(self) -> Result<RenderConfig, String> {
+        if self.width == 0 || self.height == 0 ||
+            matches!(self.sampling, SampleMode::Uniform(0) | SampleMode::Jittered { samples: 0, .. } | SampleMode::Pattern { samples: 0, .. }) ||
+            matches!(self.integrator, Integrator::PathTrace { samples_per_pixel: 0, .. }) || self.max_depth < 0 {
+            return Err(String::from("Width, height and samples must be positive and depth non-negative"));
+        }
+        if let SampleMode::Pattern { pattern: SamplePattern::Halton(base_x, base_y), .. } = self.sampling {
+            if base_x < 2 || base_y < 2 {
+                return Err(String::from("Halton bases must be at least 2"));
+            }
+        }
+        if self.lights.iter().any(|light| matches!(*light, Light::Area { samples: 0, .. })) {
+            return Err(String::from("Area lights need at least one sample"));
+        }
+        if self.fog_density.is_nan() || self.fog_density < 0.0 {
+            return Err(String::from("Fog density must be non-negative"));
+        }
+        if self.indirect.is_nan() || self.indirect < 0.0 {
+            return Err(String::from("Indirect weight must be non-negative"));
+        }
+        if self.shadow_bias.is_nan() || self.shadow_bias < 0.0 {
+            return Err(String::from("Shadow bias must be non-negative"));
+        }
+        if self.exposure.is_nan() || self.exposure < 0.0 {
+            return Err(String::from("Exposure must be non-negative"));
+        }
+        if self.gamma.is_nan() || self.gamma <= 0.0 {
+            return Err(String::from("Gamma must be positive"));
+        }
+        Ok(self)
+    }
+}
+
+/// Diffuse and specular light that reaches the hit point from `light`.
+fn do_ray_trace(config: &RenderConfig, ray: Ray, scene: &dyn Scene, hit: &Hit, light: &Light) -> Vector3d {
+    let point: Vector3d = ray.orig + ray.dir * hit.lambda;
+    let (direction, distance, intensity) = light.illuminate(point);
+    let g: f64 = hit.normal.dot(direction);
+    if g >= 0.0 {
+        return Vector3d::ZERO;
+    }
+
+    let origin: Vector3d = point + hit.normal * surface_bias(config, &ray, hit);
+    let visibility: f64 = light.visibility(scene, origin, direction, distance);
+    if visibility == 0.0 {
+        return Vector3d::ZERO;
+    }
+    let diffuse: Vector3d = (-g * hit.material.diffuse) * hit.material.color * intensity;
+    let lit: Vector3d = if hit.material.specular > 0.0 {
+        let s: f64 = direction.reflect(hit.normal).dot(-ray.dir).max(0.0);
+        diffuse + hit.material.specular * s.powf(hit.material.shininess) * intensity
+    } else {
+        diffuse
+    };
+    visibility * lit
+}
+
+pub fn ray_trace(config: &RenderConfig, ray: Ray, scene: &dyn Scene, nesting: i32) -> Vector3d {
+    let mut hit: Hit = match (scene.hit(&ray), config.mode) {
+        (None, mode) if mode.is_shaded() => return config.background.color(&ray),
+        (None, _) => return Vector3d::ZERO,
+        (Some(hit), RenderMode::Depth { far }) => {
+            let v: f64 = (1.0 - hit.lambda / far).max(0.0);
+            return Vector3d::new(v, v, v);
+        }
+        (Some(hit), RenderMode::Normal) => return 0.5 * hit.normal + Vector3d::new(0.5, 0.5, 0.5),
+        (Some(hit), RenderMode::Wireframe { width, color })
+            if hit.barycentric.is_some_and(|b| b.iter().any(|&w| w < width)) => return color,
+        (Some(hit), _) => hit,
+    };
+    if hit.material.color_fn.is_some() || hit.material.texture.is_some() {
+        hit.material.color = hit.material.color_at(ray.orig + ray.dir * hit.lambda, hit.uv);
+    }
+    let lit: Vector3d = 1.0 - config.lights.iter()
+        .map(|light| {
+            do_ray_trace(config, ray, scene, &hit, light)
+        })
+        .fold(Vector3d::ONE, |a, b| { a * (1.0 - b) });
+    let reflectivity: f64 = hit.material.reflectivity;
+    let reflection_color: Vector3d = if reflectivity > 0.0 && nesting < config.max_depth {
+        let point: Vector3d = ray.orig + ray.dir * hit.lambda;
+        let reflection = Ray::new(point + hit.normal * surface_bias(config, &ray, &hit), ray.dir.reflect(hit.normal));
+        reflectivity * ray_trace(config, reflection, scene, nesting + 1)
+    } else {
+        Vector3d::ZERO
+    };
+    let albedo: Vector3d = hit.material.diffuse * hit.material.color;
+    let indirect: Vector3d = if config.indirect > 0.0 && nesting < config.max_depth {
+        config.indirect * albedo * bounce(config, ray, scene, nesting, &hit)
+    } else {
+        Vector3d::ZERO
+    };
+    let surface: Vector3d = 1.0 - (1.0 - lit) * (1.0 - reflection_color) +
+        config.ambient * albedo + indirect + hit.material.emission;
+    let t: f64 = hit.material.transparency;
+    let color: Vector3d = if t > 0.0 && nesting < config.max_depth {
+        (1.0 - t) * surface + t * transmit(config, ray, scene, nesting, &hit)
+    } else {
+        surface
+    };
+    if config.fog_density > 0.0 {
+        let f: f64 = 1.0 - (-config.fog_density * hit.lambda).exp();
+        color.lerp(config.fog_color, f)
+    } else {
+        color
+    }
+}
+
+/// Traces one ray in a cosine-distributed direction off the side of the
+/// surface that was hit. The direction is seeded by the hit point, so that
+/// renders stay deterministic.
+fn bounce(config: &RenderConfig, ray: Ray, scene: &dyn Scene, nesting: i32, hit: &Hit) -> Vector3d {
+    let point: Vector3d = ray.orig + ray.dir * hit.lambda;
+    let normal: Vector3d = if hit.normal.dot(ray.dir) > 0.0 { -hit.normal } else { hit.normal };
+    let mut rng = SplitMix64(point.x.to_bits() ^ point.y.to_bits().rotate_left(21) ^ point.z.to_bits().rotate_left(42));
+    let dir: Vector3d = Vector3d::sample_cosine_hemisphere(normal, &mut rng);
+    ray_trace(config, Ray::new(point + normal * surface_bias(config, &ray, hit), dir), scene, nesting + 1)
+}
+
+/// Estimates the light arriving along `ray` by following a random path of
+/// up to `max_depth` bounces. At every bounce the path picks up the
+/// emission of the surface and the direct light from the `lights`, and then
+/// continues as a mirror reflection with probability `reflectivity` or else
+/// in a cosine-distributed direction, weighted by the surface color as
+/// albedo. From the third bounce on, paths with little weight left are ended
+/// early by Russian roulette. Specular highlights, transparency, ambient
+/// light and fog are ignored.
+pub fn path_trace(config: &RenderConfig, ray: Ray, scene: &dyn Scene, rng: &mut impl Rng) -> Vector3d {
+    let mut ray: Ray = ray;
+    let mut color = Vector3d::ZERO;
+    let mut weight = Vector3d::ONE;
+    for depth in 0..=config.max_depth {
+        let mut hit: Hit = match scene.hit(&ray) {
+            None => return color + weight * config.background.color(&ray),
+            Some(hit) => hit,
+        };
+        color += weight * hit.material.emission;
+        let point: Vector3d = ray.orig + ray.dir * hit.lambda;
+        if hit.material.color_fn.is_some() || hit.material.texture.is_some() {
+            hit.material.color = hit.material.color_at(point, hit.uv);
+        }
+        // Bounce off the side that was hit, also for the inside of closed
+        // objects
+        let normal: Vector3d = if hit.normal.dot(ray.dir) > 0.0 { -hit.normal } else { hit.normal };
+        let origin: Vector3d = point + normal * surface_bias(config, &ray, &hit);
+        let albedo: Vector3d = hit.material.diffuse * hit.material.color;
+        let reflectivity: f64 = hit.material.reflectivity;
+        let direct: Vector3d = config.lights.iter()
+            .map(|light| {
+                let (direction, distance, intensity) = light.illuminate(point);
+                let g: f64 = normal.dot(direction);
+                if g >= 0.0 {
+                    Vector3d::ZERO
+                } else {
+                    (-g * light.visibility(scene, origin, direction, distance)) * intensity
+                }
+            })
+            .sum();
+        color += (1.0 - reflectivity) * weight * albedo * direct;
+        if depth == config.max_depth {
+            break;
+        }
+        let dir: Vector3d = if rng.next_f64() < reflectivity {
+            ray.dir.reflect(normal)
+        } else {
+            weight *= albedo;
+            Vector3d::sample_cosine_hemisphere(normal, rng)
+        };
+        if depth >= 2 {
+            let survival: f64 = weight.x.max(weight.y).max(weight.z).min(1.0);
+            if rng.next_f64() >= survival {
+                break;
+            }
+            weight = weight / survival;
+        }
+        ray = Ray::new(origin, dir);
+    }
+    color
+}
+
+/// Distance by which rays leaving a surface start off it, so that rounding
+/// errors in the hit point do not make them hit the same surface again. The
+/// error grows with the magnitude of the coordinates, so the bias scales
+/// with the distance travelled from the ray origin and with the origin's
+/// distance from the world origin.
+fn surface_bias(config: &RenderConfig, ray: &Ray, hit: &Hit) -> f64 {
+    config.shadow_bias * (ray.orig.length() + hit.lambda * ray.dir.length())
+}
+
+/// Schlick's approximation of the Fresnel reflectance for a surface
+/// between air and a medium with index of refraction `ior`.
+fn schlick(cos: f64, ior: f64) -> f64 {
+    let r0: f64 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
+/// Color seen through a transparent surface: the refracted and reflected
+/// rays weighted by the Fresnel reflectance. On total internal reflection
+/// only the reflected ray remains.
+fn transmit(config: &RenderConfig, ray: Ray, scene: &dyn Scene, nesting: i32, hit: &Hit) -> Vector3d {
+    let entering: bool = ray.dir.dot(hit.normal) < 0.0;
+    let (n, eta) = if entering {
+        (hit.normal, 1.0 / hit.material.ior)
+    } else {
+        (-hit.normal, hit.material.ior)
+    };
+    let point: Vector3d = ray.orig + ray.dir * hit.lambda;
+    let bias: Vector3d = n * surface_bias(config, &ray, hit);
+    let reflected = Ray::new(point + bias, ray.dir.reflect(n));
+    match ray.dir.refract(n, eta) {
+        None => ray_trace(config, reflected, scene, nesting + 1),
+        Some(dir) => {
+            let cos: f64 = if entering { -ray.dir.dot(n) } else { -dir.dot(n) };
+            let r: f64 = schlick(cos, hit.material.ior);
+            let refracted = Ray::new(point - bias, dir);
+            r * ray_trace(config, reflected, scene, nesting + 1) +
+                (1.0 - r) * ray_trace(config, refracted, scene, nesting + 1)
+        }
+    }
+}
+
+/// Applies gamma correction to a linear color, clamping it to `[0, 1]`
+/// before and after. A gamma of 1.0 leaves the color unchanged.
+pub fn gamma_correct(color: Vector3d, gamma: f64) -> Vector3d {
+    let c: Vector3d = color.clamp_scalar(0.0, 1.0);
+    if gamma == 1.0 {
+        return c;
+    }
+    let e: f64 = 1.0 / gamma;
+    Vector3d::new(c.x.powf(e), c.y.powf(e), c.z.powf(e)).clamp_scalar(0.0, 1.0)
+}
+
+/// Replaces NaN and infinite channels, as produced by degenerate geometry,
+/// with 0 so that they neither spread through the accumulated samples nor
+/// end up as arbitrary bytes in the image.
+fn sanitize(color: Vector3d) -> Vector3d {
+    if color.is_finite() {
+        color
+    } else {
+        Vector3d::from(color.to_array().map(|v| if v.is_finite() { v } else { 0.0 }))
+    }
+}
+
+/// Returns the average color of pixel `(x, y)` and the number of primary
+/// rays that were traced for it.
+pub fn sample_pixel(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, x: u32, y: u32) -> (Vector3d, u32) {
+    let path_rng: Cell<SplitMix64> = Cell::new(match config.integrator {
+        Integrator::PathTrace { seed, .. } => SplitMix64::for_pixel(x, y, seed),
+        Integrator::Whitted => SplitMix64(0),
+    });
+    let trace = |sx: f64, sy: f64| {
+        stats::primary_ray();
+        let ray: Ray = camera.ray_for_pixel(sx, sy, config.width, config.height);
+        let color: Vector3d = match (config.integrator, config.mode) {
+            (Integrator::PathTrace { samples_per_pixel, .. }, RenderMode::Shaded) => {
+                let mut rng: SplitMix64 = path_rng.get();
+                let paths: Vector3d = (0..samples_per_pixel)
+                    .map(|_| path_trace(config, ray, scene, &mut rng))
+                    .sum();
+                path_rng.set(rng);
+                paths / f64::from(samples_per_pixel)
+            }
+            _ => ray_trace(config, ray, scene, 0),
+        };
+        debug_assert!(color.is_finite(), "Non-finite color {} at pixel ({}, {})", color, x, y);
+        sanitize(color)
+    };
+    sample_with(config, x, y, trace)
+}
+
+/// Averages the samples that `config.sampling` takes of pixel `(x, y)`,
+/// where `trace` returns the color at a position on the image plane.
+fn sample_with<T: Fn(f64, f64) -> Vector3d>(config: &RenderConfig, x: u32, y: u32, trace: T) -> (Vector3d, u32) {
+    match config.sampling {
+        SampleMode::Uniform(ss) => {
+            let sample_scale: f64 = 1.0 / (f64::from(ss) * f64::from(ss));
+            let g: Vector3d = (0..ss)
+                .flat_map(|dx| (0..ss).map(move |dy| (dx, dy)))
+                .map(|(dx, dy)| trace(
+                    f64::from(x) + f64::from(dx) / f64::from(ss),
+                    f64::from(y) + f64::from(dy) / f64::from(ss)))
+                .sum();
+            (g * sample_scale, ss * ss)
+        }
+        SampleMode::Jittered { samples: ss, seed } => {
+            let mut rng = SplitMix64::for_pixel(x, y, seed);
+            let sample_scale: f64 = 1.0 / (f64::from(ss) * f64::from(ss));
+            let mut g = Vector3d::ZERO;
+            for dx in 0..ss {
+                for dy in 0..ss {
+                    g += trace(
+                        f64::from(x) + (f64::from(dx) + rng.next_f64()) / f64::from(ss),
+                        f64::from(y) + (f64::from(dy) + rng.next_f64()) / f64::from(ss));
+                }
+            }
+            (g * sample_scale, ss * ss)
+        }
+        SampleMode::Pattern { pattern, samples } => {
+            let offsets: Vec<(f64, f64)> = pattern.offsets(samples);
+            let g: Vector3d = offsets
+                .iter()
+                .map(|&(dx, dy)| trace(f64::from(x) + dx, f64::from(y) + dy))
+                .sum();
+            (g / offsets.len() as f64, offsets.len() as u32)
+        }
+        SampleMode::Adaptive { max_depth, threshold } => {
+            let mut sampler = AdaptiveSampler { trace, threshold, samples: 0 };
+            let (x, y) = (f64::from(x), f64::from(y));
+            let corners = [
+                sampler.trace(x, y),
+                sampler.trace(x + 1.0, y),
+                sampler.trace(x, y + 1.0),
+                sampler.trace(x + 1.0, y + 1.0),
+            ];
+            let color = sampler.sample(x, y, 1.0, corners, max_depth);
+            (color, sampler.samples)
+        }
+    }
+}
+
+struct AdaptiveSampler<T> {
+    trace: T,
+    threshold: f64,
+    samples: u32,
+}
+
+impl<T: Fn(f64, f64) -> Vector3d> AdaptiveSampler<T> {
+    fn trace(&mut self, x: f64, y: f64) -> Vector3d {
+        self.samples += 1;
+        (self.trace)(x, y)
+    }
+
+    /// Averages the square at `(x, y)` with the given side, given the colors
+    /// at its corners in the order top-left, top-right, bottom-left,
+    /// bottom-right.
+    fn sample(&mut self, x: f64, y: f64, size: f64, corners: [Vector3d; 4], depth: u32) -> Vector3d {
+        let mean: Vector3d = corners.iter().sum::<Vector3d>() / 4.0;
+        let variance: f64 = corners.iter().map(|c| c.distance_squared(mean)).sum::<f64>() / 4.0;
+        if depth == 0 || variance <= self.threshold {
+            return mean;
+        }
+        let h = size / 2.0;
+        let [top_left, top_right, bottom_left, bottom_right] = corners;
+        let top = self.trace(x + h, y);
+        let left = self.trace(x, y + h);
+        let center = self.trace(x + h, y + h);
+        let right = self.trace(x + size, y + h);
+        let bottom = self.trace(x + h, y + size);
+        (self.sample(x, y, h, [top_left, top, left, center], depth - 1) +
+            self.sample(x + h, y, h, [top, top_right, center, right], depth - 1) +
+            self.sample(x, y + h, h, [left, center, bottom_left, bottom], depth - 1) +
+            self.sample(x + h, y + h, h, [center, right, bottom, bottom_right], depth - 1)) / 4.0
+    }
+}
+
+/// Stands in for the scene at pixels that cannot hit it.
+struct Empty;
+
+impl Scene for Empty {
+    fn hit(&self, _ray: &Ray) -> Option<Hit> {
+        None
+    }
+
+    fn shadow(&self, _ray: &Ray) -> bool {
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::EMPTY
+    }
+}
+
+/// The pixel rectangle, see `Camera::screen_bounds`, that the scene's
+/// bounding box projects to, grown by a pixel to absorb rounding.
+fn visible_bounds(scene: &dyn Scene, camera: &Camera, config: &RenderConfig) -> Option<(f64, f64, f64, f64)> {
+    camera
+        .screen_bounds(scene.bounding_box(), config.width, config.height)
+        .map(|(x0, y0, x1, y1)| (x0 - 1.0, y0 - 1.0, x1 + 1.0, y1 + 1.0))
+}
+
+/// Pixels outside `visible` are rendered against an empty scene, which
+/// skips all intersection tests but still samples the background.
+fn render_pixel(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, visible: Option<(f64, f64, f64, f64)>,
+                x: u32, y: u32) -> [u8; 3] {
+    let (fx, fy) = (f64::from(x), f64::from(y));
+    let culled: bool = visible.is_some_and(|(x0, y0, x1, y1)| fx + 1.0 < x0 || fx > x1 || fy + 1.0 < y0 || fy > y1);
+    let scene: &dyn Scene = if culled { &Empty } else { scene };
+    let (color, _) = sample_pixel(scene, camera, config, x, y);
+    let color: Vector3d = if config.mode.is_shaded() {
+        gamma_correct(config.tone_map.apply(color * config.exposure), config.gamma)
+    } else {
+        gamma_correct(color, 1.0)
+    };
+    let c: Vector3d = Vector3d::new(0.5, 0.5, 0.5) + color * 255.0;
+    c.to_array().map(|v| v as u8)
+}
+
+/// Renders the image without reporting progress.
+pub fn render(scene: &dyn Scene, camera: &Camera, config: &RenderConfig) -> Image {
+    render_with_progress(scene, camera, config, |_, _| {})
+}
+
+/// Calls `on_progress` with the number of completed rows and the image
+/// height after each finished row, or with the number of completed tiles
+/// and the tile count when `config.tile_size` is positive.
+pub fn render_with_progress<F>(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, on_progress: F) -> Image
+    where F: FnMut(u32, u32) + Send
+{
+    render_with_stats(scene, camera, config, on_progress).0
+}
+
+/// Like `render_with_progress`, and also returns how many rays and
+/// intersection tests the render took and how long it took.
+pub fn render_with_stats<F>(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, on_progress: F) -> (Image, RenderStats)
+    where F: FnMut(u32, u32) + Send
+{
+    let start = Instant::now();
+    let (image, mut stats) = render_rect(scene, camera, config, Rect { x0: 0, y0: 0, x1: config.width, y1: config.height }, on_progress);
+    stats.elapsed = start.elapsed();
+    (image, stats)
+}
+
+/// Renders the pixels `x0..x1` of the image rows `y0..y1`, counted from the
+/// top-left like the pixels of an `Image`. The primary rays are those of
+/// the full `config.width` × `config.height` frame, so regions can be
+/// stitched together into the full image.
+pub fn render_region(scene: &dyn Scene, camera: &Camera, config: &RenderConfig,
+                     x0: u32, y0: u32, x1: u32, y1: u32) -> Image {
+    assert!(x0 <= x1 && x1 <= config.width && y0 <= y1 && y1 <= config.height,
+            "Region {}..{} x {}..{} is outside the image", x0, x1, y0, y1);
+    render_rect(scene, camera, config, Rect { x0, y0, x1, y1 }, |_, _| {}).0
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Rect {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+/// Rows and tiles complete in any order, but the callback is serialized, so
+/// the count always increases by one. The statistics of each row or tile
+/// are collected from the counters of the thread that rendered it.
+struct Progress<F> {
+    state: Mutex<(u32, RenderStats, F)>,
+    total: u32,
+}
+
+impl<F: FnMut(u32, u32)> Progress<F> {
+    fn new(total: u32, on_progress: F) -> Self {
+        Progress { state: Mutex::new((0, RenderStats::default(), on_progress)), total }
+    }
+
+    fn started(&self) {
+        stats::take();
+    }
+
+    fn done(&self) {
+        let part_stats: RenderStats = stats::take();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (ref mut done, ref mut total_stats, ref mut on_progress) = *state;
+        *done += 1;
+        *total_stats += part_stats;
+        on_progress(*done, self.total);
+    }
+
+    fn stats(self) -> RenderStats {
+        self.state.into_inner().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
+
+/// Splits `rect` into tiles of at most `size` × `size` pixels, left to
+/// right and top to bottom.
+fn tiles(rect: Rect, size: u32) -> Vec<Rect> {
+    (rect.y0..rect.y1)
+        .step_by(size as usize)
+        .flat_map(|y0| (rect.x0..rect.x1).step_by(size as usize).map(move |x0| Rect {
+            x0,
+            y0,
+            x1: (x0 + size).min(rect.x1),
+            y1: (y0 + size).min(rect.y1),
+        }))
+        .collect()
+}
+
+/// Renders the pixels of `tile` in the order of an `Image`.
+fn render_tile(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, visible: Option<(f64, f64, f64, f64)>,
+               tile: Rect) -> Vec<[u8; 3]> {
+    (tile.y0..tile.y1)
+        .flat_map(|row| (tile.x0..tile.x1).map(move |x| (x, config.height - 1 - row)))
+        .map(|(x, y)| render_pixel(scene, camera, config, visible, x, y))
+        .collect()
+}
+
+/// Copies the pixels of `tile` into `image`, which holds the pixels of
+/// `rect`.
+fn blit(image: &mut Image, rect: Rect, tile: Rect, pixels: &[[u8; 3]]) {
+    let width: usize = (tile.x1 - tile.x0) as usize;
+    for (row, line) in pixels.chunks(width).enumerate() {
+        let y: usize = (tile.y0 - rect.y0) as usize + row;
+        let start: usize = y * image.width as usize + (tile.x0 - rect.x0) as usize;
+        image.pixels[start..start + width].copy_from_slice(line);
+    }
+}
+
+/// Renders `rect` on `config.threads` worker threads (0 picks the number of
+/// available cores). With a `tile_size`, the threads take tiles from a
+/// shared queue until it runs dry, so that threads that got cheap tiles
+/// help out with the rest. Otherwise rows are handed out round-robin so
+/// that every thread gets a similar mix of busy and empty rows.
+#[cfg(not(feature = "rayon"))]
+fn render_rect<F>(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, rect: Rect, on_progress: F) -> (Image, RenderStats)
+    where F: FnMut(u32, u32) + Send
+{
+    let (width, height) = (rect.x1 - rect.x0, rect.y1 - rect.y0);
+    let mut image = Image::new(width, height);
+    if width == 0 || height == 0 {
+        return (image, RenderStats::default());
+    }
+    let threads: usize = if config.threads == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        config.threads
+    };
+    let visible = visible_bounds(scene, camera, config);
+    if config.tile_size > 0 {
+        let mut queue: Vec<Rect> = tiles(rect, config.tile_size);
+        // Pop from the back, so that the tiles start at the top
+        queue.reverse();
+        let progress = Progress::new(queue.len() as u32, on_progress);
+        let queue: Mutex<Vec<Rect>> = Mutex::new(queue);
+        let done: Vec<(Rect, Vec<[u8; 3]>)> = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| s.spawn(|| {
+                    let mut done = Vec::new();
+                    while let Some(tile) = queue.lock().unwrap_or_else(|e| e.into_inner()).pop() {
+                        progress.started();
+                        done.push((tile, render_tile(scene, camera, config, visible, tile)));
+                        progress.done();
+                    }
+                    done
+                }))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        });
+        for (tile, pixels) in done {
+            blit(&mut image, rect, tile, &pixels);
+        }
+        return (image, progress.stats());
+    }
+    let progress = Progress::new(height, on_progress);
+    let progress_ref = &progress;
+    let mut work: Vec<Vec<_>> = (0..threads).map(|_| Vec::new()).collect();
+    for (row, line) in image.pixels.chunks_mut(width as usize).enumerate() {
+        work[row % threads].push((config.height - 1 - rect.y0 - row as u32, line));
+    }
+    thread::scope(|s| {
+        for rows in work {
+            s.spawn(move || {
+                for (y, line) in rows {
+                    progress_ref.started();
+                    for (x, pixel) in line.iter_mut().enumerate() {
+                        *pixel = render_pixel(scene, camera, config, visible, rect.x0 + x as u32, y);
+                    }
+                    progress_ref.done();
+                }
+            });
+        }
+    });
+    (image, progress.stats())
+}
+
+/// Renders the tiles or rows of `rect` with rayon, which balances the load
+/// by itself. `config.threads` limits the size of the thread pool (0 uses
+/// rayon's default).
+#[cfg(feature = "rayon")]
+fn render_rect<F>(scene: &dyn Scene, camera: &Camera, config: &RenderConfig, rect: Rect, on_progress: F) -> (Image, RenderStats)
+    where F: FnMut(u32, u32) + Send
+{
+    use rayon::prelude::*;
+
+    let (width, height) = (rect.x1 - rect.x0, rect.y1 - rect.y0);
+    let mut image = Image::new(width, height);
+    if width == 0 || height == 0 {
+        return (image, RenderStats::default());
+    }
+    let visible = visible_bounds(scene, camera, config);
+    let queue: Vec<Rect> = if config.tile_size > 0 { tiles(rect, config.tile_size) } else { Vec::new() };
+    let progress = Progress::new(if queue.is_empty() { height } else { queue.len() as u32 }, on_progress);
+    let fill = |image: &mut Image| {
+        if !queue.is_empty() {
+            let done: Vec<(Rect, Vec<[u8; 3]>)> = queue
+                .par_iter()
+                .map(|&tile| {
+                    progress.started();
+                    let pixels: Vec<[u8; 3]> = render_tile(scene, camera, config, visible, tile);
+                    progress.done();
+                    (tile, pixels)
+                })
+                .collect();
+            for (tile, pixels) in done {
+                blit(image, rect, tile, &pixels);
+            }
+            return;
+        }
+        image.pixels.par_chunks_mut(width as usize).enumerate().for_each(|(row, line)| {
+            let y = config.height - 1 - rect.y0 - row as u32;
+            progress.started();
+            for (x, pixel) in line.iter_mut().enumerate() {
+                *pixel = render_pixel(scene, camera, config, visible, rect.x0 + x as u32, y);
+            }
+            progress.done();
+        });
+    };
+    match rayon::ThreadPoolBuilder::new().num_threads(config.threads).build() {
+        Ok(pool) => pool.install(|| fill(&mut image)),
+        Err(_) => fill(&mut image),
+    }
+    (image, progress.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts;
+    use std::time::Duration;
+    use {AxisAlignedBox, Group, Material, Plane, Sphere, Triangle};
+
+    fn config(lights: &[Light], max_depth: i32) -> RenderConfig {
+        RenderConfig { lights: lights.to_vec(), max_depth, ..RenderConfig::default() }
+    }
+
+    #[test]
+    fn halton_offsets_are_spread_over_the_pixel() {
+        let offsets: Vec<(f64, f64)> = SamplePattern::Halton(2, 3).offsets(4);
+        assert_eq!(offsets.len(), 16);
+        assert!(offsets.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+        // Every quadrant of the pixel gets a fair share of the samples
+        for &(qx, qy) in &[(0.0, 0.0), (0.5, 0.0), (0.0, 0.5), (0.5, 0.5)] {
+            let count: usize = offsets.iter().filter(|&&(x, y)| qx <= x && x < qx + 0.5 && qy <= y && y < qy + 0.5).count();
+            assert!((3..=5).contains(&count), "{} samples in quadrant ({}, {})", count, qx, qy);
+        }
+        assert_eq!(offsets[..3], [(0.5, 1.0 / 3.0), (0.25, 2.0 / 3.0), (0.75, 1.0 / 9.0)]);
+    }
+
+    #[test]
+    fn halton_offsets_are_deterministic() {
+        assert_eq!(SamplePattern::Halton(2, 3).offsets(3), SamplePattern::Halton(2, 3).offsets(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Halton bases must be at least 2")]
+    fn halton_offsets_reject_bases_below_two() {
+        SamplePattern::Halton(1, 3).offsets(2);
+    }
+
+    #[test]
+    fn halton_bases_below_two_are_rejected() {
+        let halton = |base_x, base_y| RenderConfig::default()
+            .sampling(SampleMode::Pattern { pattern: SamplePattern::Halton(base_x, base_y), samples: 2 })
+            .build();
+        assert!(halton(1, 3).is_err());
+        assert!(halton(2, 0).is_err());
+        assert!(halton(2, 3).is_ok());
+    }
+
+    #[test]
+    fn diffuse_scales_the_material_color() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let lights = [Light::Directional { direction: Vector3d::new(0.0, 0.0, 1.0), color: Vector3d::ONE }];
+        let color = Vector3d::new(1.0, 0.5, 0.25);
+        let sphere = |diffuse: f64| Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, Material::new(color, 0.0, diffuse));
+        assert_eq!(ray_trace(&config(&lights, 1), ray, &sphere(1.0), 0), color);
+        assert_eq!(ray_trace(&config(&lights, 1), ray, &sphere(0.5), 0), color * 0.5);
+    }
+
+    #[test]
+    fn reflectivity_weights_the_reflection() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        // The first light hits the mirror, the second the matte sphere behind the camera
+        let lights = [
+            Light::Directional { direction: Vector3d::new(0.0, 0.0, 1.0), color: Vector3d::ONE },
+            Light::Directional { direction: Vector3d::new(0.0, -1.0, -1.0).normalize(), color: Vector3d::ONE },
+        ];
+        let scene = |reflectivity: f64| {
+            let objects: Vec<Box<dyn Scene>> = vec![
+                Box::new(Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, Material::new(Vector3d::ONE, reflectivity, 0.0))),
+                Box::new(Sphere::new(Vector3d::new(0.0, 0.0, -4.0), 1.0, Material::new(Vector3d::ONE, 0.0, 1.0))),
+            ];
+            Group::new(objects)
+        };
+        let mirror: Vector3d = ray_trace(&config(&lights, 1), ray, &scene(1.0), 0);
+        assert!((mirror.x - consts::FRAC_1_SQRT_2).abs() < 1e-9, "{}", mirror);
+        assert_eq!(ray_trace(&config(&lights, 1), ray, &scene(0.5), 0), mirror * 0.5);
+        assert_eq!(ray_trace(&config(&lights, 1), ray, &scene(0.0), 0), Vector3d::ZERO);
+    }
+
+    #[test]
+    fn sphere_stays_round_in_wide_image() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, Material::from_color(Vector3d::ONE));
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        let covered = |x: u32, y: u32| {
+            let ray: Ray = camera.ray_for_pixel(f64::from(x) + 0.5, f64::from(y) + 0.5, 80, 40);
+            sphere.ray_sphere(&ray) < f64::INFINITY
+        };
+        let columns: usize = (0..80).filter(|&x| (0..40).any(|y| covered(x, y))).count();
+        let rows: usize = (0..40).filter(|&y| (0..80).any(|x| covered(x, y))).count();
+        assert!(rows > 10 && rows < 40, "{} rows", rows);
+        assert!((columns as i64 - rows as i64).abs() <= 1, "{} columns, {} rows", columns, rows);
+    }
+
+    /// Two half-reflective spheres facing each other across the origin, each
+    /// lit by its own light.
+    fn facing_spheres() -> Group {
+        let material = Material::new(Vector3d::ONE, 0.5, 0.5);
+        Group::new(vec![
+            Box::new(Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, material.clone())),
+            Box::new(Sphere::new(Vector3d::new(0.0, 0.0, -4.0), 1.0, material)),
+        ])
+    }
+
+    #[test]
+    fn deeper_reflections_add_light() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let lights = [
+            Light::Directional { direction: Vector3d::new(0.0, -1.0, 1.0).normalize(), color: Vector3d::ONE },
+            Light::Directional { direction: Vector3d::new(0.0, -1.0, -1.0).normalize(), color: Vector3d::ONE },
+        ];
+        let none: Vector3d = ray_trace(&config(&lights, 0), ray, &facing_spheres(), 0);
+        let shallow: Vector3d = ray_trace(&config(&lights, 1), ray, &facing_spheres(), 0);
+        let deep: Vector3d = ray_trace(&config(&lights, 4), ray, &facing_spheres(), 0);
+        assert!(0.0 < none.x && none.x < shallow.x && shallow.x < deep.x, "{} {} {}", none, shallow, deep);
+    }
+
+    #[test]
+    fn mid_gray_is_gamma_corrected() {
+        let gray = Vector3d::new(0.5, 0.5, 0.5);
+        assert!((gamma_correct(gray, 2.2).x - 0.5f64.powf(1.0 / 2.2)).abs() < 1e-12);
+        assert_eq!(gamma_correct(gray, 1.0), gray);
+        assert_eq!(gamma_correct(Vector3d::new(-1.0, 2.0, 1.0), 2.2), Vector3d::new(0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn missed_ray_returns_background() {
+        let color = Vector3d::new(0.1, 0.2, 0.3);
+        let env = RenderConfig { background: Background::Solid(color), ..config(&[], 1) };
+        let nothing = Group::new(Vec::new());
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        assert_eq!(ray_trace(&env, ray, &nothing, 0), color);
+        let (bottom, top) = (Vector3d::ONE, Vector3d::new(0.5, 0.7, 1.0));
+        let sky = RenderConfig { background: Background::Gradient { bottom, top }, ..env };
+        assert_eq!(ray_trace(&sky, Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0)), &nothing, 0), top);
+        assert_eq!(ray_trace(&sky, Ray::new(Vector3d::ZERO, Vector3d::new(0.0, -1.0, 0.0)), &nothing, 0), bottom);
+        assert_eq!(ray_trace(&sky, ray, &nothing, 0), bottom.lerp(top, 0.5));
+    }
+
+    fn small() -> RenderConfig {
+        RenderConfig { width: 37, height: 29, sampling: SampleMode::Uniform(1), threads: 3, ..RenderConfig::default() }
+    }
+
+    /// A sphere on a floor, seen from above the floor.
+    fn scene() -> (Group, Camera) {
+        let scene = Group::new(vec![
+            Box::new(Sphere::new(Vector3d::new(0.0, 1.0, 0.0), 1.0, Material::from_color(Vector3d::new(1.0, 0.2, 0.2)))),
+            Box::new(Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), Material::from_color(Vector3d::ONE))),
+        ]);
+        let camera = Camera::new(Vector3d::new(0.0, 1.5, -4.0), Vector3d::new(0.0, -0.1, 1.0), Vector3d::new(0.0, 1.0, 0.0), 50.0);
+        (scene, camera)
+    }
+
+    /// Renders pixel by pixel on the current thread.
+    fn serial(scene: &dyn Scene, camera: &Camera, config: &RenderConfig) -> Image {
+        let visible = visible_bounds(scene, camera, config);
+        let mut image = Image::new(config.width, config.height);
+        for row in 0..config.height {
+            for x in 0..config.width {
+                image.pixels[(row * config.width + x) as usize] = render_pixel(scene, camera, config, visible, x, config.height - 1 - row);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn threads_match_serial_render() {
+        let (scene, camera) = scene();
+        let expected: Image = serial(&scene, &camera, &small());
+        for &threads in &[1, 2, 5] {
+            assert!(render(&scene, &camera, &RenderConfig { threads, ..small() }) == expected, "{} threads", threads);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn rayon_matches_serial_render() {
+        let (scene, camera) = scene();
+        let expected: Image = serial(&scene, &camera, &small());
+        assert!(render(&scene, &camera, &RenderConfig { threads: 0, ..small() }) == expected);
+        assert!(render(&scene, &camera, &small()) == expected);
+    }
+
+    fn matte(color: Vector3d) -> Material {
+        Material::new(color, 0.0, 1.0)
+    }
+
+    #[test]
+    fn near_point_light_is_brighter() {
+        let floor = Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), matte(Vector3d::ONE));
+        let ray = Ray::new(Vector3d::new(0.0, 0.5, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        let at = |height: f64| [Light::Point { position: Vector3d::new(0.0, height, 0.0), intensity: Vector3d::ONE }];
+        let near: Vector3d = ray_trace(&config(&at(1.0), 0), ray, &floor, 0);
+        let far: Vector3d = ray_trace(&config(&at(3.0), 0), ray, &floor, 0);
+        assert!((near.x - 1.0).abs() < 1e-9 && (far.x - 1.0 / 9.0).abs() < 1e-9, "{} {}", near, far);
+        // A sphere above the light does not shade the floor
+        let scene = Group::new(vec![
+            Box::new(floor),
+            Box::new(Sphere::new(Vector3d::new(0.0, 5.0, 0.0), 1.0, matte(Vector3d::ONE))),
+        ]);
+        assert_eq!(ray_trace(&config(&at(1.0), 0), ray, &scene, 0), near);
+    }
+
+    #[test]
+    fn colored_lights_tint_white_sphere() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, matte(Vector3d::ONE));
+        let lights = [
+            Light::Directional { direction: Vector3d::new(1.0, 0.0, 0.0), color: Vector3d::new(1.0, 0.0, 0.0) },
+            Light::Directional { direction: Vector3d::new(-1.0, 0.0, 0.0), color: Vector3d::new(0.0, 1.0, 0.0) },
+        ];
+        let side = |x: f64| ray_trace(&config(&lights, 0), Ray::new(Vector3d::new(x, 0.0, 0.0), Vector3d::new(0.0, 0.0, 1.0)), &sphere, 0);
+        let left: Vector3d = side(-0.9);
+        assert!((left.x - 0.9).abs() < 1e-9 && left.y == 0.0 && left.z == 0.0, "{}", left);
+        let right: Vector3d = side(0.9);
+        assert!(right.x == 0.0 && (right.y - 0.9).abs() < 1e-9 && right.z == 0.0, "{}", right);
+    }
+
+    #[test]
+    fn highlight_at_mirror_angle() {
+        let floor = Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), matte(Vector3d::ONE).with_specular(0.5, 20.0));
+        let lights = [Light::Directional { direction: Vector3d::new(1.0, -1.0, 0.0).normalize(), color: Vector3d::ONE }];
+        let view = |dir: Vector3d| ray_trace(&config(&lights, 0), Ray::new(Vector3d::new(0.0, 1.0, 0.0), dir.normalize()), &floor, 0);
+        let mirror: Vector3d = view(Vector3d::new(-1.0, -1.0, 0.0));
+        let off: Vector3d = view(Vector3d::new(1.0, -1.0, 0.0));
+        let diffuse: f64 = consts::FRAC_1_SQRT_2;
+        assert!((off.x - diffuse).abs() < 1e-9, "{}", off);
+        assert!((mirror.x - (diffuse + 0.5)).abs() < 1e-9, "{}", mirror);
+    }
+
+    #[test]
+    fn ambient_lights_shadowed_side() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, matte(Vector3d::new(1.0, 0.5, 0.5)));
+        let lights = [Light::Directional { direction: Vector3d::new(0.0, 0.0, -1.0), color: Vector3d::ONE }];
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        assert_eq!(ray_trace(&config(&lights, 0), ray, &sphere, 0), Vector3d::ZERO);
+        let ambient = RenderConfig { ambient: Vector3d::new(0.1, 0.1, 0.1), ..config(&lights, 0) };
+        assert_eq!(ray_trace(&ambient, ray, &sphere, 0), Vector3d::new(0.1, 0.05, 0.05));
+    }
+
+    #[test]
+    fn wall_shows_through_glass_sphere() {
+        let scene = |material: Material| Group::new(vec![
+            Box::new(Plane::new(Vector3d::new(0.0, 0.0, 10.0), Vector3d::new(0.0, 0.0, -1.0), matte(Vector3d::new(1.0, 0.0, 0.0)))),
+            Box::new(Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, material)),
+        ]);
+        let ambient = RenderConfig { ambient: Vector3d::new(0.5, 0.5, 0.5), ..config(&[], 3) };
+        let ray = Ray::new(Vector3d::new(0.2, 0.1, 0.0), Vector3d::new(0.0, 0.0, 1.0));
+        let glass: Vector3d = ray_trace(&ambient, ray, &scene(Material::new(Vector3d::ONE, 0.0, 0.0).with_transparency(1.0, 1.5)), 0);
+        // Apart from a faint Fresnel reflection, only the red wall shows
+        assert!(glass.x > 0.4 && glass.y < 0.01 && glass.z < 0.01, "{}", glass);
+        let opaque: Vector3d = ray_trace(&ambient, ray, &scene(matte(Vector3d::ONE)), 0);
+        assert_eq!(opaque, Vector3d::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn render_returns_pixels_of_image() {
+        let (scene, camera) = scene();
+        let image: Image = render(&scene, &camera, &RenderConfig { width: 4, height: 4, ..small() });
+        assert_eq!((image.width, image.height), (4, 4));
+        assert_eq!(image.as_bytes().len(), 4 * 4 * 3);
+    }
+
+    #[test]
+    fn progress_runs_once_per_row_on_any_threads() {
+        let (scene, camera) = scene();
+        for &threads in &[1, 0] {
+            let mut counts: Vec<u32> = Vec::new();
+            render_with_progress(&scene, &camera, &RenderConfig { threads, ..small() }, |done, total| {
+                assert_eq!(total, 29);
+                counts.push(done);
+            });
+            assert_eq!(counts, (1..=29).collect::<Vec<u32>>(), "{} threads", threads);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling_works_harder_on_edges() {
+        // The left side of the box ends in the middle of column 16
+        let wall = AxisAlignedBox::new(Vector3d::new(-100.0, -100.0, 5.0), Vector3d::new(0.15, 100.0, 6.0), matte(Vector3d::ONE));
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 90.0);
+        let config = RenderConfig {
+            width: 32,
+            height: 32,
+            sampling: SampleMode::Adaptive { max_depth: 3, threshold: 0.001 },
+            ..RenderConfig::default()
+        };
+        let (_, edge) = sample_pixel(&wall, &camera, &config, 16, 16);
+        let (_, inside) = sample_pixel(&wall, &camera, &config, 3, 16);
+        let (_, outside) = sample_pixel(&wall, &camera, &config, 28, 16);
+        assert_eq!((inside, outside), (4, 4));
+        assert!(edge > 4, "{} samples on the edge", edge);
+        let (color, _) = sample_pixel(&wall, &camera, &config, 16, 16);
+        let (full, _) = sample_pixel(&wall, &camera, &config, 3, 16);
+        assert!(color.x > 0.2 * full.x && color.x < 0.8 * full.x, "{} of {}", color.x, full.x);
+    }
+
+    #[test]
+    fn jittered_render_is_reproducible() {
+        let (scene, camera) = scene();
+        let jittered = |seed: u64| render(&scene, &camera, &RenderConfig { sampling: SampleMode::Jittered { samples: 2, seed }, ..small() });
+        assert!(jittered(7) == jittered(7));
+        assert!(jittered(7) != jittered(8));
+        assert!(jittered(7) != render(&scene, &camera, &RenderConfig { sampling: SampleMode::Uniform(2), ..small() }));
+    }
+
+    #[test]
+    fn normal_mode_encodes_normal_towards_camera() {
+        let wall = Plane::new(Vector3d::new(0.0, 0.0, 5.0), Vector3d::new(0.0, 0.0, 1.0), matte(Vector3d::ONE));
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let normal = RenderConfig { mode: RenderMode::Normal, background: Background::Solid(Vector3d::ONE), ..RenderConfig::default() };
+        assert_eq!(ray_trace(&normal, ray, &wall, 0), Vector3d::new(0.5, 0.5, 0.0));
+        assert_eq!(ray_trace(&normal, ray, &Group::new(Vec::new()), 0), Vector3d::ZERO);
+        let depth = |far: f64| ray_trace(&RenderConfig { mode: RenderMode::Depth { far }, ..RenderConfig::default() }, ray, &wall, 0);
+        assert_eq!(depth(10.0), Vector3d::new(0.5, 0.5, 0.5));
+        assert_eq!(depth(4.0), Vector3d::ZERO);
+    }
+
+    #[test]
+    fn color_fn_is_evaluated_at_the_hit_point() {
+        fn by_height(point: Vector3d) -> Vector3d {
+            if point.y > 0.0 { Vector3d::new(1.0, 0.0, 0.0) } else { Vector3d::new(0.0, 0.0, 1.0) }
+        }
+        let wall = Plane::new(Vector3d::new(0.0, 0.0, 5.0), Vector3d::new(0.0, 0.0, -1.0), matte(Vector3d::ONE).with_color_fn(by_height));
+        let config = RenderConfig { lights: vec![Light::Directional { direction: Vector3d::new(0.0, 0.0, 1.0), color: Vector3d::ONE }], ..RenderConfig::default() };
+        let at = |y: f64| ray_trace(&config, Ray::new(Vector3d::new(0.0, y, 0.0), Vector3d::new(0.0, 0.0, 1.0)), &wall, 0);
+        let (high, low) = (at(1.0), at(-1.0));
+        assert!(high.x > 0.5 && high.z == 0.0, "{}", high);
+        assert!(low.z > 0.5 && low.x == 0.0, "{}", low);
+    }
+
+    #[test]
+    fn reinhard_keeps_bright_values_below_one() {
+        let bright = Vector3d::new(10.0, 10.0, 10.0);
+        let mapped: Vector3d = ToneMap::Reinhard.apply(bright);
+        assert!((mapped.x - 10.0 / 11.0).abs() < 1e-12);
+        assert!(gamma_correct(mapped, 2.2).x < 1.0);
+        assert_eq!(ToneMap::None.apply(bright), bright);
+        assert_eq!(gamma_correct(ToneMap::None.apply(bright), 2.2), Vector3d::ONE);
+    }
+
+    #[test]
+    fn fog_fades_far_spheres_towards_fog_color() {
+        let blue = Vector3d::new(0.0, 0.0, 1.0);
+        let config = RenderConfig { fog_density: 0.05, fog_color: blue, ..RenderConfig::default() };
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        let at = |config: &RenderConfig, z: f64| ray_trace(config, ray, &Sphere::new(Vector3d::new(0.0, 0.0, z), 1.0, matte(Vector3d::ONE)), 0);
+        let (near, far) = (at(&config, 3.0), at(&config, 30.0));
+        assert!(far.distance(blue) < near.distance(blue), "{} {}", near, far);
+        let clear = RenderConfig { fog_density: 0.0, fog_color: blue, ..RenderConfig::default() };
+        assert_eq!(at(&clear, 30.0), at(&RenderConfig::default(), 30.0));
+    }
+
+    #[test]
+    fn quadrants_stitch_into_the_full_frame() {
+        let (scene, camera) = scene();
+        let config = small();
+        let full: Image = render(&scene, &camera, &config);
+        let mut stitched = Image::new(config.width, config.height);
+        let (mx, my) = (18, 11);
+        for &(x0, y0, x1, y1) in &[(0, 0, mx, my), (mx, 0, config.width, my), (0, my, mx, config.height), (mx, my, config.width, config.height)] {
+            let region: Image = render_region(&scene, &camera, &config, x0, y0, x1, y1);
+            assert_eq!((region.width, region.height), (x1 - x0, y1 - y0));
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    stitched.pixels[(y * config.width + x) as usize] = region.pixels[((y - y0) * region.width + x - x0) as usize];
+                }
+            }
+        }
+        assert_eq!(stitched, full);
+    }
+
+    #[test]
+    fn pixels_far_from_the_scene_box_skip_intersection_tests() {
+        let scene = Sphere::new(Vector3d::new(5.0, 4.0, 10.0), 0.5, matte(Vector3d::ONE));
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        let config = RenderConfig { width: 40, height: 30, sampling: SampleMode::Uniform(1), threads: 2, ..RenderConfig::default() };
+        let (_, stats) = render_with_stats(&scene, &camera, &config, |_, _| {});
+        assert_eq!(stats.primary_rays, 40 * 30);
+        assert!(stats.intersection_tests > 0 && stats.intersection_tests < 100, "{:?}", stats);
+
+        let visible = visible_bounds(&scene, &camera, &config);
+        stats::take();
+        // The sphere projects into the top right corner, y counting upwards
+        let (x0, y0, _, _) = visible.unwrap();
+        assert!(x0 > 20.0 && y0 > 15.0, "{:?}", visible);
+        render_pixel(&scene, &camera, &config, visible, 0, 0);
+        assert_eq!(stats::take().intersection_tests, 0);
+    }
+
+    #[test]
+    fn single_pixel_render_counts_its_rays() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, matte(Vector3d::ONE));
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 10.0);
+        let config = RenderConfig {
+            width: 1,
+            height: 1,
+            sampling: SampleMode::Uniform(1),
+            threads: 1,
+            max_depth: 0,
+            lights: vec![Light::Directional { direction: Vector3d::new(0.0, 0.0, 1.0), color: Vector3d::ONE }],
+            ..RenderConfig::default()
+        };
+        let (_, stats) = render_with_stats(&sphere, &camera, &config, |_, _| {});
+        assert_eq!((stats.primary_rays, stats.shadow_rays, stats.intersection_tests), (1, 1, 2));
+    }
+
+    fn scaled_scene(s: f64) -> (Group, Camera) {
+        let scene = Group::new(vec![
+            Box::new(Sphere::new(Vector3d::new(0.0, 1.0, 0.0) * s, s, matte(Vector3d::new(1.0, 0.2, 0.2)))),
+            Box::new(Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), matte(Vector3d::ONE))),
+        ]);
+        let camera = Camera::new(Vector3d::new(0.3, 1.5, -4.0) * s, Vector3d::new(0.0, -0.1, 1.0), Vector3d::new(0.0, 1.0, 0.0), 50.0);
+        (scene, camera)
+    }
+
+    /// Number of channels differing by more than one level.
+    fn differences(a: &Image, b: &Image) -> usize {
+        a.pixels.iter().zip(&b.pixels)
+            .flat_map(|(p, q)| p.iter().zip(q).filter(|&(u, v)| (i32::from(*u) - i32::from(*v)).abs() > 1))
+            .count()
+    }
+
+    #[test]
+    fn surface_bias_scales_with_the_scene() {
+        let config = RenderConfig {
+            width: 64,
+            height: 48,
+            sampling: SampleMode::Uniform(1),
+            lights: vec![Light::Directional { direction: Vector3d::new(-1.0, -2.0, 1.0), color: Vector3d::ONE }],
+            ..RenderConfig::default()
+        };
+        let render_at = |s: f64, config: &RenderConfig| {
+            let (scene, camera) = scaled_scene(s);
+            render(&scene, &camera, config)
+        };
+        let unit: Image = render_at(1.0, &config);
+        assert_eq!(differences(&unit, &render_at(1e6, &config)), 0);
+        assert!(differences(&unit, &render_at(1e6, &RenderConfig { shadow_bias: 0.0, ..config.clone() })) > 0);
+    }
+
+    /// A closed box with white walls apart from the left one.
+    fn cornell_box(left: Vector3d) -> Group {
+        let white = matte(Vector3d::new(0.8, 0.8, 0.8));
+        let wall = |point: Vector3d, normal: Vector3d, material: Material| -> Box<dyn Scene> { Box::new(Plane::new(point, normal, material)) };
+        Group::new(vec![
+            wall(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), white.clone()),
+            wall(Vector3d::new(0.0, 2.0, 0.0), Vector3d::new(0.0, -1.0, 0.0), white.clone()),
+            wall(Vector3d::new(-1.0, 0.0, 0.0), Vector3d::new(1.0, 0.0, 0.0), matte(left)),
+            wall(Vector3d::new(1.0, 0.0, 0.0), Vector3d::new(-1.0, 0.0, 0.0), white.clone()),
+            wall(Vector3d::new(0.0, 0.0, 2.0), Vector3d::new(0.0, 0.0, -1.0), white.clone()),
+            wall(Vector3d::new(0.0, 0.0, -2.0), Vector3d::new(0.0, 0.0, 1.0), white),
+        ])
+    }
+
+    #[test]
+    fn path_tracing_bleeds_color_between_walls() {
+        let light = Light::Point { position: Vector3d::new(0.0, 1.9, 0.0), intensity: Vector3d::ONE };
+        let config = RenderConfig { lights: vec![light], max_depth: 4, ..RenderConfig::default() };
+        let ray = Ray::new(Vector3d::new(0.0, 1.0, -1.5), Vector3d::new(-0.9, 0.0, 3.5));
+        let average = |scene: &Group| {
+            let mut rng = SplitMix64(1);
+            (0..4000).map(|_| path_trace(&config, ray, scene, &mut rng)).sum::<Vector3d>() / 4000.0
+        };
+        let gray: Vector3d = average(&cornell_box(Vector3d::new(0.8, 0.8, 0.8)));
+        assert!((gray.x - gray.y).abs() < 1e-12 && gray.x > 0.0, "{}", gray);
+        let tinted: Vector3d = average(&cornell_box(Vector3d::new(0.8, 0.1, 0.1)));
+        assert!(tinted.x > 1.2 * tinted.y, "{}", tinted);
+    }
+
+    #[test]
+    fn emissive_sphere_lights_the_floor() {
+        let camera = Camera::new(Vector3d::new(0.0, 1.5, -4.0), Vector3d::new(0.0, -0.3, 1.0), Vector3d::new(0.0, 1.0, 0.0), 50.0);
+        let config = RenderConfig {
+            width: 24,
+            height: 16,
+            sampling: SampleMode::Uniform(1),
+            lights: vec![],
+            background: Background::Solid(Vector3d::ZERO),
+            ambient: Vector3d::ZERO,
+            integrator: Integrator::PathTrace { samples_per_pixel: 8, seed: 3 },
+            ..RenderConfig::default()
+        };
+        let scene = |emission: Vector3d| Group::new(vec![
+            Box::new(Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), matte(Vector3d::ONE))),
+            Box::new(Sphere::new(Vector3d::new(0.0, 1.0, 0.0), 0.5, matte(Vector3d::ONE).with_emission(emission))),
+        ]);
+        let lit: Image = render(&scene(Vector3d::new(4.0, 4.0, 4.0)), &camera, &config);
+        let bottom = &lit.pixels[(lit.width * (lit.height - 1)) as usize..];
+        assert!(bottom.iter().any(|p| p.iter().any(|&c| c > 0)));
+        let dark: Image = render(&scene(Vector3d::ZERO), &camera, &config);
+        assert!(dark.pixels.iter().all(|&p| p == [0, 0, 0]));
+    }
+
+    #[test]
+    fn path_traced_render_is_reproducible() {
+        let (scene, camera) = scene();
+        let traced = |seed: u64| render(&scene, &camera, &RenderConfig {
+            integrator: Integrator::PathTrace { samples_per_pixel: 2, seed },
+            ..small()
+        });
+        assert!(traced(5) == traced(5));
+        assert!(traced(5) != traced(6));
+    }
+
+    #[test]
+    fn render_reports_elapsed_time() {
+        let (scene, camera) = scene();
+        let (_, stats) = render_with_stats(&scene, &camera, &RenderConfig {
+            sampling: SampleMode::Uniform(2),
+            ..small()
+        }, |_, _| {});
+        assert!(stats.elapsed > Duration::ZERO);
+    }
+
+    #[test]
+    fn bright_light_clips_pixels_high() {
+        let (scene, camera) = scene();
+        let lit = |intensity: f64| {
+            let config = RenderConfig {
+                lights: vec![Light::Point { position: Vector3d::new(2.0, 5.0, -3.0), intensity: Vector3d::ONE * intensity }],
+                ..small()
+            };
+            render(&scene, &camera, &config).clipping()
+        };
+        assert!(lit(1000.0).clipped_high > 0);
+        assert_eq!(lit(1.0).clipped_high, 0);
+    }
+
+    #[test]
+    fn exposure_scales_the_linear_color() {
+        let config = RenderConfig {
+            width: 1,
+            height: 1,
+            sampling: SampleMode::Uniform(1),
+            gamma: 1.0,
+            background: Background::Solid(Vector3d::new(0.25, 0.25, 0.25)),
+            ..RenderConfig::default()
+        };
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        assert_eq!(render(&Empty, &camera, &config).pixels, vec![[64, 64, 64]]);
+        assert_eq!(render(&Empty, &camera, &RenderConfig { exposure: 2.0, ..config }).pixels, vec![[128, 128, 128]]);
+    }
+
+    #[test]
+    fn mirror_shows_only_the_reflected_scene() {
+        let red = Vector3d::new(1.0, 0.0, 0.0);
+        let scene = Group::new(vec![
+            Box::new(Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, Material::mirror())),
+            Box::new(Plane::new(Vector3d::new(0.0, 0.0, -5.0), Vector3d::new(0.0, 0.0, 1.0), matte(red))),
+        ]);
+        let config = RenderConfig {
+            lights: vec![Light::Directional { direction: Vector3d::new(1.0, 0.0, -1.0).normalize(), color: Vector3d::ONE }],
+            ..RenderConfig::default()
+        };
+        let seen: Vector3d = ray_trace(&config, Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0)), &scene, 0);
+        let wall: Vector3d = ray_trace(&config, Ray::new(Vector3d::new(0.0, 0.0, 3.0), Vector3d::new(0.0, 0.0, -1.0)), &scene, 1);
+        assert!(seen.x > 0.0 && seen.y == 0.0 && seen.z == 0.0, "{}", seen);
+        assert!(seen.distance(wall) < 1e-9, "{} {}", seen, wall);
+    }
+
+    #[test]
+    fn indirect_bounce_brightens_floor_next_to_lit_wall() {
+        let scene = Group::new(vec![
+            Box::new(Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), matte(Vector3d::ONE))),
+            Box::new(Plane::new(Vector3d::new(-1.0, 0.0, 0.0), Vector3d::new(1.0, 0.0, 0.0), matte(Vector3d::ONE))),
+        ]);
+        let config = RenderConfig {
+            background: Background::Solid(Vector3d::ZERO),
+            ambient: Vector3d::ZERO,
+            lights: vec![Light::Directional { direction: Vector3d::new(-1.0, 0.0, 0.0), color: Vector3d::ONE * 4.0 }],
+            ..RenderConfig::default()
+        };
+        let floor = |config: &RenderConfig| -> f64 {
+            (0..100)
+                .map(|k| {
+                    let target = Vector3d::new(-0.5 + 0.01 * f64::from(k), 0.0, f64::from(k % 7));
+                    let ray = Ray::new(target + Vector3d::new(0.0, 1.0, -1.0), Vector3d::new(0.0, -1.0, 1.0));
+                    ray_trace(config, ray, &scene, 0).x
+                })
+                .sum()
+        };
+        assert_eq!(floor(&config), 0.0);
+        assert!(floor(&RenderConfig { indirect: 1.0, ..config.clone() }) > 1.0);
+    }
+
+    /// Hits every ray going upwards, with a NaN normal.
+    struct Degenerate;
+
+    impl Scene for Degenerate {
+        fn hit(&self, ray: &Ray) -> Option<Hit> {
+            if ray.dir.y > 0.0 {
+                Some(Hit::new(1.0, Vector3d::new(f64::NAN, 0.0, 0.0), matte(Vector3d::ONE)))
+            } else {
+                None
+            }
+        }
+
+        fn shadow(&self, _ray: &Ray) -> bool {
+            false
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            Aabb::INFINITE
+        }
+    }
+
+    #[test]
+    fn sanitize_zeroes_only_non_finite_channels() {
+        assert_eq!(sanitize(Vector3d::new(f64::NAN, 0.5, f64::INFINITY)), Vector3d::new(0.0, 0.5, 0.0));
+        assert_eq!(sanitize(Vector3d::new(0.25, 0.5, 2.0)), Vector3d::new(0.25, 0.5, 2.0));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Non-finite color")]
+    fn nan_hit_fails_the_debug_assertion() {
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        sample_pixel(&Degenerate, &camera, &small(), 0, 28);
+    }
+
+    #[test]
+    fn nan_samples_do_not_corrupt_their_pixel() {
+        let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+        let config = RenderConfig {
+            sampling: SampleMode::Uniform(3),
+            background: Background::Solid(Vector3d::new(0.25, 0.5, 0.75)),
+            ..small()
+        };
+        // Sanitizes like sample_pixel, without its debug assertion
+        let trace = |sx: f64, sy: f64| {
+            let ray: Ray = camera.ray_for_pixel(sx, sy, config.width, config.height);
+            sanitize(ray_trace(&config, ray, &Degenerate, 0))
+        };
+        // One in three rows of samples lies above the middle of the image
+        assert_eq!(sample_with(&config, 0, 14, trace), (Vector3d::new(1.5, 3.0, 4.5) * (1.0 / 9.0), 9));
+        assert_eq!(sample_with(&config, 0, 15, trace), (Vector3d::ZERO, 9));
+        assert_eq!(sample_with(&config, 0, 13, trace), (Vector3d::new(0.25, 0.5, 0.75), 9));
+    }
+
+    #[test]
+    fn wireframe_colors_edges_only() {
+        let triangle = Triangle::new(Vector3d::new(-1.0, -1.0, 5.0), Vector3d::new(1.0, -1.0, 5.0), Vector3d::new(0.0, 1.0, 5.0), matte(Vector3d::ONE));
+        let green = Vector3d::new(0.0, 1.0, 0.0);
+        let config = RenderConfig {
+            mode: RenderMode::Wireframe { width: 0.05, color: green },
+            ..RenderConfig::default()
+        };
+        let towards = |x: f64, y: f64| Ray::new(Vector3d::ZERO, Vector3d::new(x, y, 5.0).normalize());
+        assert_eq!(ray_trace(&config, towards(0.0, -0.99), &triangle, 0), green);
+        let center: Vector3d = ray_trace(&config, towards(0.0, -1.0 / 3.0), &triangle, 0);
+        assert!(center != green);
+        assert_eq!(center, ray_trace(&RenderConfig::default(), towards(0.0, -1.0 / 3.0), &triangle, 0));
+    }
+
+    #[test]
+    fn progress_counts_rows_by_default() {
+        let (scene, camera) = scene();
+        let mut calls: Vec<(u32, u32)> = Vec::new();
+        render_with_progress(&scene, &camera, &small(), |done, total| calls.push((done, total)));
+        assert_eq!(calls, (1..=29).map(|done| (done, 29)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tiles_render_the_same_image_as_rows() {
+        let (scene, camera) = scene();
+        let rows: Image = render(&scene, &camera, &small());
+        let mut calls: u32 = 0;
+        let tiled: Image = render_with_progress(&scene, &camera, &RenderConfig { tile_size: 8, ..small() }, |done, total| {
+            calls += 1;
+            assert_eq!((done, total), (calls, 20));
+        });
+        assert_eq!(calls, 20);
+        assert_eq!(tiled, rows);
+    }
+
+    #[test]
+    fn builder_keeps_the_previous_defaults() {
+        let config = RenderConfig::default().build().unwrap();
+        assert_eq!((config.width, config.height, config.sampling, config.max_depth), (512, 512, SampleMode::Uniform(4), 1));
+        assert_eq!((config.gamma, config.exposure, config.tone_map, config.mode), (2.2, 1.0, ToneMap::None, RenderMode::Shaded));
+        assert_eq!(config.lights.len(), 2);
+        let built = RenderConfig::default().width(64).samples(2).threads(3).build().unwrap();
+        assert_eq!(built, RenderConfig { width: 64, sampling: SampleMode::Uniform(2), threads: 3, ..RenderConfig::default() });
+        assert!(RenderConfig::default().height(0).build().is_err());
+        assert!(RenderConfig::default().samples(0).build().is_err());
+        assert!(RenderConfig::default().gamma(0.0).build().is_err());
+        let area = |samples: u32| Light::Area { center: Vector3d::ZERO, normal: Vector3d::new(0.0, -1.0, 0.0), radius: 1.0, intensity: Vector3d::ONE, samples };
+        assert!(RenderConfig::default().lights(vec![area(0)]).build().is_err());
+        assert!(RenderConfig::default().lights(vec![area(1)]).build().is_ok());
+    }
+}
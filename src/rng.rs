@@ -0,0 +1,69 @@
+/// A source of uniformly distributed pseudo-random numbers.
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// The splitmix64 generator: tiny, fast and good enough for sampling.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    /// Returns a generator for pixel `(x, y)` of the image rendered with
+    /// `frame_seed`. Seeding per pixel keeps the random numbers independent
+    /// of the order in which the pixels are rendered. The seed is mixed
+    /// before the pixel index goes in, so that nearby seeds do not hand the
+    /// same streams to other pixels.
+    pub fn for_pixel(x: u32, y: u32, frame_seed: u64) -> Self {
+        SplitMix64(mix(mix(frame_seed) ^ (u64::from(y) << 32 | u64::from(x))))
+    }
+}
+
+/// The splitmix64 output function, which scrambles all bits of `h`.
+fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_generators_are_deterministic_and_independent() {
+        let mut a = SplitMix64::for_pixel(3, 4, 42);
+        let mut b = SplitMix64::for_pixel(3, 4, 42);
+        let first: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..4).map(|_| b.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(SplitMix64::for_pixel(4, 3, 42).next_u64(), first[0]);
+        assert_ne!(SplitMix64::for_pixel(3, 4, 43).next_u64(), first[0]);
+    }
+
+    #[test]
+    fn neighbouring_seeds_do_not_swap_pixel_streams() {
+        // A plain xor gave seed 1 at pixel (0, 0) the stream of seed 0 at (1, 0)
+        let first = |i: u32, seed: u64| SplitMix64::for_pixel(i % 8, i / 8, seed).next_u64();
+        let seed_zero: Vec<u64> = (0..64).map(|i| first(i, 0)).collect();
+        for seed in 1..4 {
+            assert!((0..64).all(|i| !seed_zero.contains(&first(i, seed))), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn next_f64_is_in_unit_interval() {
+        let mut rng = SplitMix64(0);
+        assert!((0..1000).map(|_| rng.next_f64()).all(|v| (0.0..1.0).contains(&v)));
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io;
+
+use serde::Deserialize;
+use serde_json;
+
+use mesh::load_obj;
+use vector3d::Vector3d;
+use {AxisAlignedBox, Bvh, Camera, CheckerPlane, Cone, Csg, CsgOp, Cylinder, Disk, Light, Material, Matrix, Plane, Scene};
+use {Sphere, Torus, Transformed, Translated, Triangle};
+
+/// A primitive in a scene file, tagged with its `type`. Omitted materials
+/// take the `Material` defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Object {
+    Sphere {
+        center: Vector3d,
+        radius: f64,
+        #[serde(default)]
+        material: Material,
+    },
+    Plane {
+        point: Vector3d,
+        normal: Vector3d,
+        #[serde(default)]
+        material: Material,
+    },
+    CheckerPlane {
+        point: Vector3d,
+        normal: Vector3d,
+        #[serde(default)]
+        material: Material,
+        #[serde(default)]
+        other: Material,
+        cell_size: f64,
+    },
+    Disk {
+        center: Vector3d,
+        normal: Vector3d,
+        radius: f64,
+        #[serde(default)]
+        material: Material,
+    },
+    Triangle {
+        a: Vector3d,
+        b: Vector3d,
+        c: Vector3d,
+        #[serde(default)]
+        material: Material,
+    },
+    Box {
+        min: Vector3d,
+        max: Vector3d,
+        #[serde(default)]
+        material: Material,
+    },
+    Cylinder {
+        base: Vector3d,
+        axis: Vector3d,
+        radius: f64,
+        #[serde(default)]
+        material: Material,
+    },
+    /// A cone with its half angle in degrees.
+    Cone {
+        apex: Vector3d,
+        axis: Vector3d,
+        half_angle: f64,
+        height: f64,
+        #[serde(default)]
+        material: Material,
+    },
+    Torus {
+        center: Vector3d,
+        axis: Vector3d,
+        major_radius: f64,
+        minor_radius: f64,
+        #[serde(default)]
+        material: Material,
+    },
+    /// A Wavefront OBJ file, see `mesh::load_obj`.
+    Mesh { path: String },
+    Group { objects: Vec<Object> },
+    Translated { offset: Vector3d, object: Box<Object> },
+    /// Constructive solid geometry, see `Csg`.
+    Csg { op: CsgOp, a: Box<Object>, b: Box<Object> },
+    /// Scales the object, then rotates it by `degrees` around `axis` and
+    /// finally moves it by `offset`.
+    Transformed {
+        #[serde(default = "unit_scale")]
+        scale: Vector3d,
+        #[serde(default = "up_axis")]
+        axis: Vector3d,
+        #[serde(default)]
+        degrees: f64,
+        #[serde(default)]
+        offset: Vector3d,
+        object: Box<Object>,
+    },
+}
+
+fn unit_scale() -> Vector3d {
+    Vector3d::ONE
+}
+
+fn up_axis() -> Vector3d {
+    Vector3d::new(0.0, 1.0, 0.0)
+}
+
+impl Object {
+    pub fn build(&self) -> io::Result<Box<dyn Scene>> {
+        Ok(match *self {
+            Object::Sphere { center, radius, ref material } =>
+                Box::new(Sphere::new(center, radius, material.clone())),
+            Object::Plane { point, normal, ref material } =>
+                Box::new(Plane::new(point, normal, material.clone())),
+            Object::CheckerPlane { point, normal, ref material, ref other, cell_size } =>
+                Box::new(CheckerPlane::new(point, normal, material.clone(), other.clone(), cell_size)),
+            Object::Disk { center, normal, radius, ref material } =>
+                Box::new(Disk::new(center, normal, radius, material.clone())),
+            Object::Triangle { a, b, c, ref material } =>
+                Box::new(Triangle::new(a, b, c, material.clone())),
+            Object::Box { min, max, ref material } =>
+                Box::new(AxisAlignedBox::new(min, max, material.clone())),
+            Object::Cylinder { base, axis, radius, ref material } =>
+                Box::new(Cylinder::new(base, axis, radius, material.clone())),
+            Object::Cone { apex, axis, half_angle, height, ref material } =>
+                Box::new(Cone::new(apex, axis, half_angle.to_radians(), height, material.clone())),
+            Object::Torus { center, axis, major_radius, minor_radius, ref material } =>
+                Box::new(Torus::new(center, axis, major_radius, minor_radius, material.clone())),
+            Object::Mesh { ref path } => Box::new(load_obj(path)?),
+            Object::Group { ref objects } => Box::new(build_group(objects)?),
+            Object::Translated { offset, ref object } => Box::new(Translated::new(offset, object.build()?)),
+            Object::Csg { op, ref a, ref b } => Box::new(Csg::new(op, a.build()?, b.build()?)),
+            Object::Transformed { scale, axis, degrees, offset, ref object } => {
+                let matrix: Matrix = Matrix::rotation(axis.normalize(), degrees.to_radians()) * Matrix::scale(scale);
+                if matrix.inverse().is_none() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Transformed object has a zero scale"));
+                }
+                Box::new(Transformed::new(matrix, offset, object.build()?))
+            }
+        })
+    }
+}
+
+/// The contents of a JSON scene file. The camera and lights are optional so
+/// that a file can describe only the geometry.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub camera: Option<Camera>,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+    pub objects: Vec<Object>,
+}
+
+impl SceneFile {
+    /// Parses a scene, rejecting a camera with an aperture but no positive
+    /// `focus_distance`, which would render nothing in focus, and area
+    /// lights without samples, which would cast no light.
+    pub fn from_json(json: &str) -> io::Result<SceneFile> {
+        let file: SceneFile = serde_json::from_str(json)?;
+        if file.camera.is_some_and(|camera| camera.aperture() > 0.0 && camera.focus_distance() <= 0.0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "camera with an aperture needs a positive focus_distance"));
+        }
+        if file.lights.iter().any(|light| matches!(*light, Light::Area { samples: 0, .. })) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "area light needs at least one sample"));
+        }
+        Ok(file)
+    }
+
+    pub fn load(path: &str) -> io::Result<SceneFile> {
+        SceneFile::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the lights with their direction and normal vectors
+    /// normalized.
+    pub fn lights(&self) -> Vec<Light> {
+        self.lights
+            .iter()
+            .map(|light| match *light {
+                Light::Directional { direction, color } =>
+                    Light::Directional { direction: direction.normalize(), color },
+                Light::Area { center, normal, radius, intensity, samples } =>
+                    Light::Area { center, normal: normal.normalize(), radius, intensity, samples },
+                Light::Spot { position, direction, angle, falloff, intensity } =>
+                    Light::Spot { position, direction: direction.normalize(), angle, falloff, intensity },
+                other => other,
+            })
+            .collect()
+    }
+
+    /// Builds the objects into a bounding volume hierarchy, as are the
+    /// objects of nested groups.
+    pub fn build(&self) -> io::Result<Bvh> {
+        build_group(&self.objects)
+    }
+}
+
+fn build_group(objects: &[Object]) -> io::Result<Bvh> {
+    let objects = objects
+        .iter()
+        .map(Object::build)
+        .collect::<io::Result<Vec<Box<dyn Scene>>>>()?;
+    Ok(Bvh::new(objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Hit, Ray};
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn with_camera(lens: &str) -> String {
+        format!(r#"{{
+            "camera": {{
+                "position": [0, 1, -4], "forward": [0, 0, 1], "up": [0, 1, 0],
+                "projection": {{ "type": "perspective", "fov_degrees": 60 }}{}
+            }},
+            "objects": []
+        }}"#, lens)
+    }
+
+    #[test]
+    fn aperture_needs_focus_distance() {
+        let error = SceneFile::from_json(&with_camera(r#", "aperture": 0.5"#)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(SceneFile::from_json(&with_camera(r#", "aperture": 0.5, "focus_distance": -1"#)).is_err());
+        let file = SceneFile::from_json(&with_camera(r#", "aperture": 0.5, "focus_distance": 4"#)).unwrap();
+        assert_eq!(file.camera.unwrap().focus_distance(), 4.0);
+        assert!(SceneFile::from_json(&with_camera("")).is_ok());
+    }
+
+    #[test]
+    fn area_light_needs_samples() {
+        let area = |samples: u32| format!(r#"{{
+            "lights": [{{ "type": "area", "center": [0, 5, 0], "normal": [0, -1, 0], "radius": 1, "intensity": [1, 1, 1], "samples": {} }}],
+            "objects": []
+        }}"#, samples);
+        let error = SceneFile::from_json(&area(0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(SceneFile::from_json(&area(1)).is_ok());
+    }
+
+    const TWO_SPHERES: &str = r#"{
+        "lights": [{ "type": "point", "position": [0, 5, 0], "intensity": [2, 2, 2] }],
+        "objects": [
+            { "type": "sphere", "center": [0, 0, 10], "radius": 1 },
+            { "type": "sphere", "center": [0, 0, 5], "radius": 1, "material": { "color": [1, 0, 0], "reflectivity": 0 } }
+        ]
+    }"#;
+
+    #[test]
+    fn two_spheres_build_bvh() {
+        let file: SceneFile = SceneFile::from_json(TWO_SPHERES).unwrap();
+        assert_eq!(file.camera, None);
+        assert_eq!(file.lights(), vec![Light::Point { position: Vector3d::new(0.0, 5.0, 0.0), intensity: Vector3d::new(2.0, 2.0, 2.0) }]);
+        let bvh: Bvh = file.build().unwrap();
+        let hit: Hit = bvh.intersect(&miss(), &Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0)));
+        assert_eq!(hit.lambda, 4.0);
+        assert_eq!(hit.material, Material { color: Vector3d::new(1.0, 0.0, 0.0), reflectivity: 0.0, ..Material::default() });
+        let hit: Hit = bvh.intersect(&miss(), &Ray::new(Vector3d::new(0.0, 0.0, 20.0), Vector3d::new(0.0, 0.0, -1.0)));
+        assert_eq!(hit.lambda, 9.0);
+        assert_eq!(hit.material, Material::default());
+    }
+
+    #[test]
+    fn unknown_object_is_rejected() {
+        let error = SceneFile::from_json(r#"{ "objects": [{ "type": "teapot" }] }"#).unwrap_err();
+        assert!(error.to_string().contains("teapot"), "{}", error);
+    }
+}
@@ -0,0 +1,29 @@
+//! Built-in scenes, shared by the command-line tool and the benchmarks.
+
+use vector3d::Vector3d;
+use {Bvh, Material, Scene, Sphere};
+
+/// The sphere fractal: a sphere of radius `r` at `c`, with four spheres of
+/// half the radius on top of it, each carrying its own fractal of
+/// `level - 1` levels. Every sphere is colored by the direction of its
+/// center.
+pub fn fractal(level: i32, c: Vector3d, r: f64) -> Box<dyn Scene> {
+    let sphere: Sphere = Sphere::new(c, r, Material::from_color(c.abs().normalize()));
+    if level == 1 {
+        return Box::new(sphere);
+    }
+    let mut objects: Vec<Box<dyn Scene>> = Vec::new();
+    objects.push(Box::new(sphere));
+    let rn: f64 = 3.0 * r / 12.0f64.sqrt();
+    let mut dz: i32 = -1;
+    while dz <= 1 {
+        let mut dx: i32 = -1;
+        while dx <= 1 {
+            let c2: Vector3d = c + Vector3d::new(f64::from(dx), 1.0, f64::from(dz)) * (rn);
+            objects.push(fractal(level - 1, c2, r * 0.5));
+            dx += 2;
+        }
+        dz += 2;
+    }
+    Box::new(Bvh::new(objects))
+}
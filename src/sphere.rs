@@ -0,0 +1,225 @@
+use std::f64::consts;
+
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sphere {
+    center: Vector3d,
+    radius: f64,
+    material: Material,
+}
+
+impl Sphere {
+    pub fn new(center: Vector3d, radius: f64, material: Material) -> Self {
+        Sphere { center, radius, material }
+    }
+
+    /// Returns whether the point lies inside the sphere or on its surface.
+    pub fn contains(&self, point: Vector3d) -> bool {
+        (point - self.center).length_squared() <= self.radius * self.radius
+    }
+
+    /// Distance from the point to the surface, negative inside the sphere.
+    pub fn signed_distance(&self, point: Vector3d) -> f64 {
+        point.distance(self.center) - self.radius
+    }
+
+    /// Latitude/longitude texture coordinates of the point on the surface
+    /// in the direction of the unit vector `n` from the center: `u` runs
+    /// from 0 to 1 with the azimuth from +x towards +z, as in
+    /// `Vector3d::from_spherical`, so the seam lies in the +x direction.
+    /// `v` runs from 0 at the north pole (+y) to 1 at the south pole.
+    pub fn uv(n: Vector3d) -> (f64, f64) {
+        let u: f64 = n.z.atan2(n.x).rem_euclid(2.0 * consts::PI) / (2.0 * consts::PI);
+        let v: f64 = n.y.clamp(-1.0, 1.0).acos() / consts::PI;
+        (u, v)
+    }
+
+    pub fn ray_sphere(&self, ray: &Ray) -> f64 {
+        let v: Vector3d = self.center - ray.orig;
+        let b: f64 = v.dot(ray.dir);
+        let disc: f64 = b * b - v.dot(v) + self.radius * self.radius;
+        if v.dot(v) < self.radius * self.radius {
+            // The origin is inside, so the only intersection ahead is the
+            // exit through the far wall. Mathematically disc >= b * b here,
+            // clamp to guard against rounding for grazing rays.
+            b + disc.max(0.0).sqrt()
+        } else if disc < 0.0 {
+            f64::INFINITY
+        } else {
+            let d: f64 = disc.sqrt();
+            let t2: f64 = b + d;
+            if t2 < 0.0 {
+                f64::INFINITY
+            } else {
+                let t1: f64 = b - d;
+                if t1 > 0.0 {
+                    t1
+                } else {
+                    t2
+                }
+            }
+        }
+    }
+}
+
+impl Scene for Sphere {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_sphere(ray);
+        if l == f64::INFINITY {
+            None
+        } else {
+            let n: Vector3d = (ray.orig + ray.dir * l - self.center).normalize();
+            let hit: Hit = Hit::new(l, n, self.material.clone());
+            // Only textures need the coordinates, which are costly to compute
+            if self.material.texture.is_some() {
+                let (u, v) = Sphere::uv(n);
+                Some(hit.with_uv(u, v))
+            } else {
+                Some(hit)
+            }
+        }
+    }
+
+    /// Reports the entry and the exit ahead of the ray origin, which is just
+    /// the exit when the origin is inside.
+    fn intersect_all(&self, ray: &Ray, out: &mut Vec<Hit>) {
+        stats::intersection_test();
+        let v: Vector3d = self.center - ray.orig;
+        let b: f64 = v.dot(ray.dir);
+        let disc: f64 = b * b - v.dot(v) + self.radius * self.radius;
+        if disc < 0.0 {
+            return;
+        }
+        let d: f64 = disc.sqrt();
+        for l in [b - d, b + d] {
+            if l > 0.0 {
+                let n: Vector3d = (ray.orig + ray.dir * l - self.center).normalize();
+                let hit: Hit = Hit::new(l, n, self.material.clone());
+                out.push(if self.material.texture.is_some() {
+                    let (u, v) = Sphere::uv(n);
+                    hit.with_uv(u, v)
+                } else {
+                    hit
+                });
+            }
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        let v: Vector3d = self.center - ray.orig;
+        let b: f64 = v.dot(ray.dir);
+        let disc: f64 = b * b - v.dot(v) + self.radius * self.radius;
+        if disc < 0.0 {
+            false
+        } else {
+            b + disc.sqrt() >= 0.0
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::around(self.center, Vector3d::new(self.radius, self.radius, self.radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use image::Image;
+    use texture::Texture;
+    use Group;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_from_center_hits_far_wall() {
+        let ray = Ray::new(Vector3d::new(0.0, 0.0, 5.0), Vector3d::new(0.0, 1.0, 0.0));
+        assert_eq!(unit_sphere().ray_sphere(&ray), 1.0);
+        assert_eq!(unit_sphere().intersect(&miss(), &ray).normal, Vector3d::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn ray_just_inside_surface_exits() {
+        // Just inside the near wall, heading across and back out
+        let across = Ray::new(Vector3d::new(0.0, 0.0, 4.0 + 1e-9), Vector3d::new(0.0, 0.0, 1.0));
+        assert!((unit_sphere().ray_sphere(&across) - 2.0).abs() < 1e-8);
+        // Just inside the far wall, heading out through it
+        let out = Ray::new(Vector3d::new(0.0, 0.0, 6.0 - 1e-9), Vector3d::new(0.0, 0.0, 1.0));
+        let t: f64 = unit_sphere().ray_sphere(&out);
+        assert!(t > 0.0 && t < 1e-8, "{}", t);
+        // Grazing along the inside of the surface
+        let grazing = Ray::new(Vector3d::new(0.0, 1.0 - 1e-12, 5.0), Vector3d::new(1.0, 0.0, 0.0));
+        assert!(unit_sphere().ray_sphere(&grazing) < 1e-5);
+    }
+
+    #[test]
+    fn ray_from_outside_hits_near_wall() {
+        let ray = Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0));
+        assert_eq!(unit_sphere().ray_sphere(&ray), 4.0);
+        let behind = Ray::new(Vector3d::new(0.0, 0.0, 7.0), Vector3d::new(0.0, 0.0, 1.0));
+        assert_eq!(unit_sphere().ray_sphere(&behind), f64::INFINITY);
+        assert!(!unit_sphere().shadow(&behind));
+    }
+
+    #[test]
+    fn contains_center_and_surface_but_not_outside() {
+        let sphere = Sphere::new(Vector3d::new(1.0, 2.0, 3.0), 2.0, Material::default());
+        assert!(sphere.contains(Vector3d::new(1.0, 2.0, 3.0)));
+        assert_eq!(sphere.signed_distance(Vector3d::new(1.0, 2.0, 3.0)), -2.0);
+        assert!(sphere.contains(Vector3d::new(1.0, 4.0, 3.0)));
+        assert_eq!(sphere.signed_distance(Vector3d::new(1.0, 4.0, 3.0)), 0.0);
+        assert!(!sphere.contains(Vector3d::new(4.0, 2.0, 3.0)));
+        assert_eq!(sphere.signed_distance(Vector3d::new(4.0, 2.0, 3.0)), 1.0);
+    }
+
+    #[test]
+    fn uv_at_north_pole_and_seam() {
+        let (u, v) = Sphere::uv(Vector3d::new(0.0, 1.0, 0.0));
+        assert_eq!((u, v), (0.0, 0.0));
+        let (u, v) = Sphere::uv(Vector3d::new(1.0, 0.0, 0.0));
+        assert_eq!(u, 0.0);
+        assert!((v - 0.5).abs() < 1e-12);
+        let (u, _) = Sphere::uv(Vector3d::new(0.0, 0.0, 1.0));
+        assert!((u - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hit_carries_uv_only_with_texture() {
+        let ray = Ray::new(Vector3d::new(0.0, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        let plain = Sphere::new(Vector3d::ZERO, 1.0, Material::from_color(Vector3d::ONE));
+        assert_eq!(plain.hit(&ray).unwrap().uv, None);
+        let texture = Arc::new(Texture::new(&Image::new(2, 2)));
+        let textured = Sphere::new(Vector3d::ZERO, 1.0, Material::from_color(Vector3d::ONE).with_texture(texture));
+        assert_eq!(textured.hit(&ray).unwrap().uv, Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn intersect_all_reports_entry_and_exit() {
+        let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 5.0), 1.0, Material::default());
+        let mut hits: Vec<Hit> = Vec::new();
+        sphere.intersect_all(&Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0)), &mut hits);
+        let mut lambdas: Vec<f64> = hits.iter().map(|hit| hit.lambda).collect();
+        lambdas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(lambdas, vec![4.0, 6.0]);
+
+        hits.clear();
+        sphere.intersect_all(&Ray::new(Vector3d::new(0.0, 0.0, 5.0), Vector3d::new(0.0, 0.0, 1.0)), &mut hits);
+        assert_eq!(hits.len(), 1);
+
+        let group = Group::new(vec![Box::new(sphere.clone()), Box::new(Sphere::new(Vector3d::new(0.0, 0.0, 10.0), 1.0, Material::default()))]);
+        hits.clear();
+        group.intersect_all(&Ray::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0)), &mut hits);
+        assert_eq!(hits.len(), 4);
+    }
+}
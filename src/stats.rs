@@ -0,0 +1,56 @@
+use std::cell::Cell;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Counts of the work done for a render.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct RenderStats {
+    /// Rays traced from the camera, including those of culled pixels.
+    pub primary_rays: u64,
+    /// Rays traced towards lights to test for occlusion.
+    pub shadow_rays: u64,
+    /// Calls of `Scene::intersect` and `Scene::shadow` on primitives; groups
+    /// and transforms are not counted themselves.
+    pub intersection_tests: u64,
+    /// Wall-clock time of the whole render.
+    pub elapsed: Duration,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.primary_rays += other.primary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.intersection_tests += other.intersection_tests;
+        self.elapsed += other.elapsed;
+    }
+}
+
+// The counters are per thread so that counting needs no synchronization;
+// the renderer collects them after every row or tile.
+thread_local! {
+    static PRIMARY_RAYS: Cell<u64> = const { Cell::new(0) };
+    static SHADOW_RAYS: Cell<u64> = const { Cell::new(0) };
+    static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub(crate) fn primary_ray() {
+    PRIMARY_RAYS.with(|c| c.set(c.get() + 1));
+}
+
+pub(crate) fn shadow_ray() {
+    SHADOW_RAYS.with(|c| c.set(c.get() + 1));
+}
+
+pub(crate) fn intersection_test() {
+    INTERSECTION_TESTS.with(|c| c.set(c.get() + 1));
+}
+
+/// Returns the counts of the current thread and resets them.
+pub(crate) fn take() -> RenderStats {
+    RenderStats {
+        primary_rays: PRIMARY_RAYS.with(|c| c.replace(0)),
+        shadow_rays: SHADOW_RAYS.with(|c| c.replace(0)),
+        intersection_tests: INTERSECTION_TESTS.with(|c| c.replace(0)),
+        elapsed: Duration::ZERO,
+    }
+}
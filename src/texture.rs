@@ -0,0 +1,62 @@
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+#[cfg(feature = "png")]
+use std::path::Path;
+
+#[cfg(feature = "png")]
+use image::read_png;
+use image::{read_ppm, Image};
+use vector3d::Vector3d;
+
+/// An image mapped onto a surface by its `(u, v)` coordinates, with `(0, 0)`
+/// at the top-left corner of the image and `(1, 1)` at the bottom-right.
+#[derive(Clone, PartialEq)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    /// Linear colors, row by row from the top-left pixel.
+    pixels: Vec<Vector3d>,
+}
+
+impl Texture {
+    /// Converts the gamma-encoded pixels of the image to the linear colors
+    /// that the renderer works with, the inverse of the output gamma of 2.2.
+    pub fn new(image: &Image) -> Self {
+        let pixels: Vec<Vector3d> = image.pixels
+            .iter()
+            .map(|p| Vector3d::from(p.map(|c| (f64::from(c) / 255.0).powf(2.2))))
+            .collect();
+        Texture { width: image.width, height: image.height, pixels }
+    }
+
+    /// Loads a PPM file, or a PNG file by its extension when built with the
+    /// `png` feature.
+    pub fn load(path: &str) -> io::Result<Texture> {
+        #[cfg(feature = "png")]
+        {
+            if Path::new(path).extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) {
+                return Ok(Texture::new(&read_png(Path::new(path))?));
+            }
+        }
+        Ok(Texture::new(&read_ppm(&mut BufReader::new(File::open(path)?))?))
+    }
+
+    /// Returns the color of the pixel at `(u, v)`. The image repeats along
+    /// `u`, so that it wraps around seams, and is clamped along `v`.
+    pub fn sample(&self, u: f64, v: f64) -> Vector3d {
+        if self.pixels.is_empty() {
+            return Vector3d::ZERO;
+        }
+        let x: u32 = ((u.rem_euclid(1.0) * f64::from(self.width)) as u32).min(self.width - 1);
+        let y: u32 = ((v.clamp(0.0, 1.0) * f64::from(self.height)) as u32).min(self.height - 1);
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Texture({}x{})", self.width, self.height)
+    }
+}
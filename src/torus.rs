@@ -0,0 +1,189 @@
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+/// A ring around `axis` through `center`: the points at `minor_radius` from
+/// the circle of `major_radius` in the plane perpendicular to the axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Torus {
+    center: Vector3d,
+    axis: Vector3d,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    pub fn new(center: Vector3d, axis: Vector3d, major_radius: f64, minor_radius: f64, material: Material) -> Self {
+        Torus { center, axis: axis.normalize(), major_radius, minor_radius, material }
+    }
+
+    pub fn ray_torus(&self, ray: &Ray) -> f64 {
+        let (rr, r) = (self.major_radius, self.minor_radius);
+        let d: Vector3d = ray.dir.normalize();
+        let scale: f64 = ray.dir.length();
+        // Start from where the ray enters the bounding sphere; keeping the
+        // coefficients small preserves precision in the quartic.
+        let o: Vector3d = ray.orig - self.center;
+        let m: f64 = o.dot(d);
+        let bound: f64 = rr + r;
+        let disc: f64 = m * m - (o.dot(o) - bound * bound);
+        if disc < 0.0 {
+            return f64::INFINITY;
+        }
+        let start: f64 = (-m - disc.sqrt()).max(0.0);
+        let o: Vector3d = o + d * start;
+
+        let m: f64 = o.dot(d);
+        let oa: f64 = o.dot(self.axis);
+        let da: f64 = d.dot(self.axis);
+        let k: f64 = o.dot(o) + rr * rr - r * r;
+        let r4: f64 = 4.0 * rr * rr;
+        let coefficients = [
+            4.0 * m,
+            4.0 * m * m + 2.0 * k - r4 * (1.0 - da * da),
+            4.0 * m * k - 2.0 * r4 * (m - oa * da),
+            k * k - r4 * (o.dot(o) - oa * oa),
+        ];
+        solve_quartic(coefficients)
+            .into_iter()
+            .map(|t| t + start)
+            .filter(|&t| t > f64::EPSILON.sqrt())
+            .fold(f64::INFINITY, f64::min) / scale
+    }
+}
+
+/// Returns the real roots of `x^4 + c[0] x^3 + c[1] x^2 + c[2] x + c[3]`,
+/// found with Ferrari's method and polished with Newton's method.
+fn solve_quartic(c: [f64; 4]) -> Vec<f64> {
+    let [a, b, cc, d] = c;
+    // Depressed quartic y^4 + p y^2 + q y + r with x = y - a / 4
+    let a2: f64 = a * a;
+    let p: f64 = b - 3.0 / 8.0 * a2;
+    let q: f64 = cc - 0.5 * a * b + a2 * a / 8.0;
+    let r: f64 = d - 0.25 * a * cc + a2 * b / 16.0 - 3.0 / 256.0 * a2 * a2;
+
+    let mut roots: Vec<f64> = Vec::with_capacity(4);
+    if q.abs() < 1e-12 {
+        for z in solve_quadratic(p, r) {
+            if z >= 0.0 {
+                roots.push(z.sqrt());
+                roots.push(-z.sqrt());
+            }
+        }
+    } else {
+        // A positive root of the resolvent cubic splits the quartic into
+        // two quadratics.
+        let m: f64 = largest_cubic_root(p, 0.25 * p * p - r, -q * q / 8.0);
+        let s: f64 = (2.0 * m).max(0.0).sqrt();
+        if s > 0.0 {
+            roots.extend(solve_quadratic(-s, 0.5 * p + m + q / (2.0 * s)));
+            roots.extend(solve_quadratic(s, 0.5 * p + m - q / (2.0 * s)));
+        }
+    }
+    roots
+        .into_iter()
+        .map(|y| {
+            let mut x: f64 = y - 0.25 * a;
+            for _ in 0..2 {
+                let f: f64 = (((x + a) * x + b) * x + cc) * x + d;
+                let df: f64 = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + cc;
+                if df.abs() > f64::EPSILON {
+                    x -= f / df;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+/// Returns the real roots of `x^2 + b x + c`.
+fn solve_quadratic(b: f64, c: f64) -> Vec<f64> {
+    let disc: f64 = b * b - 4.0 * c;
+    if disc < 0.0 {
+        return Vec::new();
+    }
+    // Avoids cancellation between -b and the square root.
+    let h: f64 = -0.5 * (b + b.signum() * disc.sqrt());
+    if h == 0.0 {
+        vec![0.0]
+    } else {
+        vec![h, c / h]
+    }
+}
+
+/// Returns the largest real root of `x^3 + a x^2 + b x + c`.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let p: f64 = b - a * a / 3.0;
+    let q: f64 = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let disc: f64 = 0.25 * q * q + p * p * p / 27.0;
+    let u: f64 = if disc > 0.0 {
+        let sq: f64 = disc.sqrt();
+        (-0.5 * q + sq).cbrt() + (-0.5 * q - sq).cbrt()
+    } else {
+        let rho: f64 = (-p / 3.0).sqrt();
+        let cos: f64 = if rho == 0.0 { 0.0 } else { (-0.5 * q / (rho * rho * rho)).clamp(-1.0, 1.0) };
+        2.0 * rho * (cos.acos() / 3.0).cos()
+    };
+    let mut x: f64 = u - a / 3.0;
+    let df: f64 = (3.0 * x + 2.0 * a) * x + b;
+    if df.abs() > f64::EPSILON {
+        x -= (((x + a) * x + b) * x + c) / df;
+    }
+    x
+}
+
+impl Scene for Torus {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let l: f64 = self.ray_torus(ray);
+        if l == f64::INFINITY {
+            None
+        } else {
+            let p: Vector3d = ray.orig + ray.dir * l - self.center;
+            let ring: Vector3d = p.reject_from(self.axis).try_normalize().unwrap_or(Vector3d::ZERO);
+            let n: Vector3d = p - ring * self.major_radius;
+            Some(Hit::new(l, n.normalize(), self.material.clone()))
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_torus(ray) < f64::INFINITY
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let e: f64 = self.major_radius + self.minor_radius;
+        Aabb::around(self.center, Vector3d::new(e, e, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn ring() -> Torus {
+        Torus::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), 2.0, 0.5, Material::default())
+    }
+
+    #[test]
+    fn ray_along_axis_misses_through_the_hole() {
+        let ray = Ray::new(Vector3d::new(0.0, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0));
+        assert_eq!(ring().intersect(&miss(), &ray), miss());
+        assert!(!ring().shadow(&ray));
+    }
+
+    #[test]
+    fn rays_hit_the_outer_ring() {
+        let hit = ring().intersect(&miss(), &Ray::new(Vector3d::new(2.0, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0)));
+        assert!((hit.lambda - 4.5).abs() < 1e-9);
+        assert!(hit.normal.distance(Vector3d::new(0.0, 1.0, 0.0)) < 1e-9);
+        let hit = ring().intersect(&miss(), &Ray::new(Vector3d::new(-5.0, 0.0, 0.0), Vector3d::new(2.0, 0.0, 0.0)));
+        assert!((hit.lambda - 1.25).abs() < 1e-9);
+        assert!(hit.normal.distance(Vector3d::new(-1.0, 0.0, 0.0)) < 1e-9);
+    }
+}
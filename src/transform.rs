@@ -0,0 +1,165 @@
+use matrix::Matrix;
+use vector3d::Vector3d;
+use {Aabb, Hit, Ray, Scene};
+
+/// Places another scene at an offset, so that a primitive or group can be
+/// reused at several positions.
+pub struct Translated {
+    offset: Vector3d,
+    inner: Box<dyn Scene>,
+}
+
+impl Translated {
+    pub fn new(offset: Vector3d, inner: Box<dyn Scene>) -> Self {
+        Translated { offset, inner }
+    }
+
+    fn to_inner(&self, ray: &Ray) -> Ray {
+        Ray::new(ray.orig - self.offset, ray.dir)
+    }
+}
+
+impl Scene for Translated {
+    /// The direction is unchanged, so distances and normals carry over
+    /// as they are.
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        self.inner.hit(&self.to_inner(ray))
+    }
+
+    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
+        self.inner.intersect(i, &self.to_inner(ray))
+    }
+
+    fn intersect_all(&self, ray: &Ray, out: &mut Vec<Hit>) {
+        self.inner.intersect_all(&self.to_inner(ray), out)
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        self.inner.shadow(&self.to_inner(ray))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let bounds: Aabb = self.inner.bounding_box();
+        Aabb::new(bounds.min + self.offset, bounds.max + self.offset)
+    }
+}
+
+/// Places another scene under an affine transform: a point `p` of the inner
+/// scene appears at `matrix * p + translation`.
+pub struct Transformed {
+    matrix: Matrix,
+    inverse: Matrix,
+    translation: Vector3d,
+    inner: Box<dyn Scene>,
+}
+
+impl Transformed {
+    /// Panics if `matrix` is not invertible.
+    pub fn new(matrix: Matrix, translation: Vector3d, inner: Box<dyn Scene>) -> Self {
+        let inverse: Matrix = matrix.inverse().expect("Transformed requires an invertible matrix");
+        Transformed { matrix, inverse, translation, inner }
+    }
+
+    /// Returns the ray in object space, with a normalized direction, and the
+    /// factor that converts world-space distances to object space.
+    fn to_inner(&self, ray: &Ray) -> (Ray, f64) {
+        let dir: Vector3d = self.inverse * ray.dir;
+        let scale: f64 = dir.length();
+        (Ray::new(self.inverse * (ray.orig - self.translation), dir / scale), scale)
+    }
+}
+
+impl Scene for Transformed {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        Some(self.intersect(&Hit::MISS, ray)).filter(|hit| !hit.is_miss())
+    }
+
+    fn intersect(&self, i: &Hit, ray: &Ray) -> Hit {
+        let (inner_ray, scale) = self.to_inner(ray);
+        let limit = Hit { lambda: i.lambda * scale, ..i.clone() };
+        let hit: Hit = self.inner.intersect(&limit, &inner_ray);
+        if hit.lambda >= limit.lambda {
+            return i.clone();
+        }
+        // Normals are transformed by the inverse transpose so that they stay
+        // perpendicular to the surface under non-uniform scaling.
+        let normal: Vector3d = (self.inverse.transpose() * hit.normal).normalize();
+        Hit { lambda: hit.lambda / scale, normal, ..hit }
+    }
+
+    fn intersect_all(&self, ray: &Ray, out: &mut Vec<Hit>) {
+        let (inner_ray, scale) = self.to_inner(ray);
+        let start: usize = out.len();
+        self.inner.intersect_all(&inner_ray, out);
+        for hit in &mut out[start..] {
+            hit.lambda /= scale;
+            hit.normal = (self.inverse.transpose() * hit.normal).normalize();
+        }
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        self.inner.shadow(&self.to_inner(ray).0)
+    }
+
+    /// Encloses the transformed corners of the inner box; an unbounded inner
+    /// box stays unbounded.
+    fn bounding_box(&self) -> Aabb {
+        let bounds: Aabb = self.inner.bounding_box();
+        if !bounds.is_finite() {
+            return Aabb::INFINITE;
+        }
+        (0..8)
+            .map(|k| {
+                let corner: Vector3d = self.matrix * bounds.corner(k) + self.translation;
+                Aabb::new(corner, corner)
+            })
+            .fold(Aabb::EMPTY, Aabb::union)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts;
+    use {Material, Plane, Sphere};
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn unit_sphere() -> Box<dyn Scene> {
+        Box::new(Sphere::new(Vector3d::ZERO, 1.0, Material::default()))
+    }
+
+    #[test]
+    fn translated_sphere_is_hit_at_the_shifted_position() {
+        let moved = Translated::new(Vector3d::new(5.0, 0.0, 0.0), unit_sphere());
+        let hit: Hit = moved.intersect(&miss(), &Ray::new(Vector3d::new(5.0, 0.0, -5.0), Vector3d::new(0.0, 0.0, 1.0)));
+        assert_eq!(hit.lambda, 4.0);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 0.0, -1.0));
+        assert_eq!(moved.intersect(&miss(), &Ray::new(Vector3d::new(0.0, 0.0, -5.0), Vector3d::new(0.0, 0.0, 1.0))), miss());
+        assert_eq!(moved.bounding_box(), Aabb::new(Vector3d::new(4.0, -1.0, -1.0), Vector3d::new(6.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn non_uniform_scale_uses_the_inverse_transpose_for_normals() {
+        let ellipsoid = Transformed::new(Matrix::scale(Vector3d::new(2.0, 1.0, 1.0)), Vector3d::ZERO, unit_sphere());
+        let x: f64 = consts::SQRT_2;
+        let hit: Hit = ellipsoid.intersect(&miss(), &Ray::new(Vector3d::new(x, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0)));
+        assert!((hit.lambda - (5.0 - consts::FRAC_1_SQRT_2)).abs() < 1e-12);
+        // The gradient of x^2 / 4 + y^2 at the hit point is (x / 2, 2 y, 0)
+        assert!(hit.normal.distance(Vector3d::new(1.0, 2.0, 0.0).normalize()) < 1e-12);
+        // whereas the matrix itself would give (2, 1, 0) / sqrt(5)
+        assert!(hit.normal.distance(Vector3d::new(2.0, 1.0, 0.0).normalize()) > 0.5);
+    }
+
+    #[test]
+    fn rotated_plane_has_rotated_normal() {
+        let floor: Box<dyn Scene> = Box::new(Plane::new(Vector3d::ZERO, Vector3d::new(0.0, 1.0, 0.0), Material::default()));
+        let z = Vector3d::new(0.0, 0.0, 1.0);
+        let tilted = Transformed::new(Matrix::rotation(z, consts::FRAC_PI_4), Vector3d::ZERO, floor);
+        let hit: Hit = tilted.intersect(&miss(), &Ray::new(Vector3d::new(1.0, 5.0, 0.0), Vector3d::new(0.0, -1.0, 0.0)));
+        assert!((hit.lambda - 4.0).abs() < 1e-12);
+        assert!(hit.normal.distance(Vector3d::new(-1.0, 1.0, 0.0).normalize()) < 1e-12);
+    }
+}
@@ -0,0 +1,114 @@
+use stats;
+use vector3d::Vector3d;
+use {Aabb, Hit, Material, Ray, Scene};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    a: Vector3d,
+    b: Vector3d,
+    c: Vector3d,
+    material: Material,
+}
+
+impl Triangle {
+    pub fn new(a: Vector3d, b: Vector3d, c: Vector3d, material: Material) -> Self {
+        Triangle { a, b, c, material }
+    }
+
+    pub fn normal(&self) -> Vector3d {
+        (self.b - self.a).cross(self.c - self.a).normalize()
+    }
+
+    /// Möller–Trumbore ray/triangle intersection.
+    pub fn ray_triangle(&self, ray: &Ray) -> f64 {
+        self.ray_triangle_uv(ray).map_or(f64::INFINITY, |(t, _, _)| t)
+    }
+
+    /// Returns the distance of the hit and the weights `u` and `v` of
+    /// corners `b` and `c`, or `None` if the ray misses.
+    pub fn ray_triangle_uv(&self, ray: &Ray) -> Option<(f64, f64, f64)> {
+        let e1: Vector3d = self.b - self.a;
+        let e2: Vector3d = self.c - self.a;
+        let p: Vector3d = ray.dir.cross(e2);
+        let det: f64 = e1.dot(p);
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        let inv_det: f64 = 1.0 / det;
+        let s: Vector3d = ray.orig - self.a;
+        let u: f64 = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q: Vector3d = s.cross(e1);
+        let v: f64 = ray.dir.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t: f64 = e2.dot(q) * inv_det;
+        if t > f64::EPSILON {
+            Some((t, u, v))
+        } else {
+            None
+        }
+    }
+}
+
+impl Scene for Triangle {
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        stats::intersection_test();
+        let (l, u, v) = self.ray_triangle_uv(ray)?;
+        let n: Vector3d = self.normal();
+        let n: Vector3d = if ray.dir.dot(n) > 0.0 { -n } else { n };
+        Some(Hit::new(l, n, self.material.clone()).with_barycentric([1.0 - u - v, u, v]))
+    }
+
+    fn shadow(&self, ray: &Ray) -> bool {
+        stats::intersection_test();
+        self.ray_triangle(ray) < f64::INFINITY
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.a.min(self.b).min(self.c), self.a.max(self.b).max(self.c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn miss() -> Hit {
+        Hit::new(f64::INFINITY, Vector3d::ZERO, Material::default())
+    }
+
+    fn triangle() -> Triangle {
+        Triangle::new(Vector3d::new(0.0, 0.0, 2.0), Vector3d::new(3.0, 0.0, 2.0), Vector3d::new(0.0, 3.0, 2.0),
+                      Material::from_color(Vector3d::ONE))
+    }
+
+    #[test]
+    fn ray_through_centroid_hits() {
+        let ray = Ray::new(Vector3d::new(1.0, 1.0, 0.0), Vector3d::new(0.0, 0.0, 1.0));
+        let hit: Hit = triangle().intersect(&miss(), &ray);
+        assert!((hit.lambda - 2.0).abs() < 1e-12);
+        assert_eq!(hit.normal, Vector3d::new(0.0, 0.0, -1.0));
+        assert!(triangle().shadow(&ray));
+        // From the other side the normal turns around
+        let back = Ray::new(Vector3d::new(1.0, 1.0, 4.0), Vector3d::new(0.0, 0.0, -1.0));
+        assert_eq!(triangle().intersect(&miss(), &back).normal, Vector3d::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_outside_edges_misses() {
+        for &(x, y) in &[(-0.1, 1.0), (1.0, -0.1), (1.6, 1.6)] {
+            let ray = Ray::new(Vector3d::new(x, y, 0.0), Vector3d::new(0.0, 0.0, 1.0));
+            assert_eq!(triangle().intersect(&miss(), &ray), miss(), "({}, {})", x, y);
+            assert!(!triangle().shadow(&ray));
+        }
+    }
+
+    #[test]
+    fn bounding_box_spans_vertices() {
+        assert_eq!(triangle().bounding_box(), Aabb::new(Vector3d::new(0.0, 0.0, 2.0), Vector3d::new(3.0, 3.0, 2.0)));
+    }
+}
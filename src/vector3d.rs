@@ -1,6 +1,15 @@
+use std::f64::consts;
+use std::fmt;
+use std::iter::Sum;
 use std::ops::*;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use rng::Rng;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(from = "[f64; 3]", into = "[f64; 3]"))]
 pub struct Vector3d {
     pub x: f64,
     pub y: f64,
@@ -8,18 +17,52 @@ pub struct Vector3d {
 }
 
 impl Vector3d {
+    pub const ZERO: Vector3d = Vector3d { x: 0.0, y: 0.0, z: 0.0 };
+    pub const ONE: Vector3d = Vector3d { x: 1.0, y: 1.0, z: 1.0 };
+
     pub fn new(x: f64, y: f64, z: f64) -> Self {
         Vector3d { x, y, z }
     }
 
+    /// Converts spherical coordinates around the y axis, which points up in
+    /// the scenes, to Cartesian ones: `theta` is the polar angle from the
+    /// +y axis and `phi` the azimuth in the xz-plane, from +x towards +z,
+    /// both in radians.
+    pub fn from_spherical(radius: f64, theta: f64, phi: f64) -> Vector3d {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        Vector3d::new(radius * sin_theta * cos_phi, radius * cos_theta, radius * sin_theta * sin_phi)
+    }
+
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+
     pub fn length(self) -> f64 {
-        self.dot(self).sqrt()
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance_squared(self, other: Vector3d) -> f64 {
+        (self - other).length_squared()
+    }
+
+    pub fn distance(self, other: Vector3d) -> f64 {
+        self.distance_squared(other).sqrt()
     }
 
     pub fn normalize(self) -> Vector3d {
         self / self.length()
     }
 
+    pub fn try_normalize(self) -> Option<Vector3d> {
+        let length = self.length();
+        if length < f64::EPSILON {
+            None
+        } else {
+            Some(self / length)
+        }
+    }
+
     pub fn dot(self, other: Vector3d) -> f64 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
@@ -30,6 +73,100 @@ impl Vector3d {
 		      self.x * other.y - self.y * other.x)
     }
 
+    pub fn angle_between(self, other: Vector3d) -> f64 {
+        (self.dot(other) / (self.length() * other.length())).clamp(-1.0, 1.0).acos()
+    }
+
+    pub fn project_onto(self, onto: Vector3d) -> Vector3d {
+        onto * (self.dot(onto) / onto.dot(onto))
+    }
+
+    pub fn reject_from(self, onto: Vector3d) -> Vector3d {
+        self - self.project_onto(onto)
+    }
+
+    /// Returns a tangent and bitangent that form an orthonormal basis together
+    /// with this (normalized) vector.
+    pub fn orthonormal_basis(self) -> (Vector3d, Vector3d) {
+        let mut axis = Vector3d::ZERO;
+        axis[self.abs().min_component().0] = 1.0;
+        let tangent = self.cross(axis).normalize();
+        let bitangent = self.cross(tangent);
+        (tangent, bitangent)
+    }
+
+    /// Returns a random unit vector in the hemisphere around the (normalized)
+    /// `normal`, with a density proportional to the cosine of its angle to
+    /// the normal, as needed for sampling diffuse reflection.
+    pub fn sample_cosine_hemisphere(normal: Vector3d, rng: &mut impl Rng) -> Vector3d {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        let u: f64 = rng.next_f64();
+        let r: f64 = u.sqrt();
+        let (sin, cos) = (2.0 * consts::PI * rng.next_f64()).sin_cos();
+        tangent * (r * cos) + bitangent * (r * sin) + normal * (1.0 - u).max(0.0).sqrt()
+    }
+
+    pub fn reflect(self, normal: Vector3d) -> Vector3d {
+        self - 2.0 * self.dot(normal) * normal
+    }
+
+    /// Refracts this unit vector through a surface with the given normal,
+    /// where `eta_ratio` is the ratio of the indices of refraction. Returns
+    /// `None` on total internal reflection.
+    pub fn refract(self, normal: Vector3d, eta_ratio: f64) -> Option<Vector3d> {
+        let cos_i = -self.dot(normal);
+        let k = 1.0 - eta_ratio * eta_ratio * (1.0 - cos_i * cos_i);
+        if k < 0.0 {
+            None
+        } else {
+            Some(eta_ratio * self + (eta_ratio * cos_i - k.sqrt()) * normal)
+        }
+    }
+
+    /// Rotates this vector around the (normalized) `axis` using Rodrigues'
+    /// rotation formula.
+    pub fn rotate_around_axis(self, axis: Vector3d, angle_radians: f64) -> Vector3d {
+        let (sin, cos) = angle_radians.sin_cos();
+        self * cos + axis.cross(self) * sin + axis * (axis.dot(self) * (1.0 - cos))
+    }
+
+    pub fn lerp(self, other: Vector3d, t: f64) -> Vector3d {
+        self * (1.0 - t) + other * t
+    }
+
+    pub fn to_array(self) -> [f64; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    /// Returns whether no component is NaN or infinite.
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    /// Returns the axis index (0, 1 or 2) and value of the largest
+    /// component, preferring the lower index on ties.
+    pub fn max_component(self) -> (usize, f64) {
+        let mut best: (usize, f64) = (0, self.x);
+        for (k, v) in [(1, self.y), (2, self.z)] {
+            if v > best.1 {
+                best = (k, v);
+            }
+        }
+        best
+    }
+
+    /// Returns the axis index (0, 1 or 2) and value of the smallest
+    /// component, preferring the lower index on ties.
+    pub fn min_component(self) -> (usize, f64) {
+        let mut best: (usize, f64) = (0, self.x);
+        for (k, v) in [(1, self.y), (2, self.z)] {
+            if v < best.1 {
+                best = (k, v);
+            }
+        }
+        best
+    }
+
     pub fn abs(self) -> Vector3d {
         Vector3d::new(self.x.abs(), self.y.abs(), self.z.abs())
     }
@@ -41,6 +178,62 @@ impl Vector3d {
     pub fn max(self, other: Vector3d) -> Vector3d {
         Vector3d::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
     }
+
+    pub fn clamp(self, min: Vector3d, max: Vector3d) -> Vector3d {
+        self.max(min).min(max)
+    }
+
+    pub fn clamp_scalar(self, lo: f64, hi: f64) -> Vector3d {
+        self.clamp(Vector3d::new(lo, lo, lo), Vector3d::new(hi, hi, hi))
+    }
+}
+
+impl Default for Vector3d {
+    fn default() -> Vector3d {
+        Vector3d::ZERO
+    }
+}
+
+impl fmt::Display for Vector3d {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({:.3}, {:.3}, {:.3})", self.x, self.y, self.z)
+    }
+}
+
+impl From<[f64; 3]> for Vector3d {
+    fn from(a: [f64; 3]) -> Vector3d {
+        Vector3d::new(a[0], a[1], a[2])
+    }
+}
+
+impl From<Vector3d> for [f64; 3] {
+    fn from(v: Vector3d) -> [f64; 3] {
+        v.to_array()
+    }
+}
+
+impl Index<usize> for Vector3d {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3d index out of range: {}", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vector3d {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vector3d index out of range: {}", axis),
+        }
+    }
 }
 
 impl Add for Vector3d {
@@ -115,6 +308,14 @@ impl Mul<f64> for Vector3d {
     }
 }
 
+impl MulAssign<f64> for Vector3d {
+    fn mul_assign(&mut self, s: f64) {
+        self.x *= s;
+        self.y *= s;
+        self.z *= s;
+    }
+}
+
 impl Mul<Vector3d> for f64 {
     type Output = Vector3d;
 
@@ -151,7 +352,19 @@ impl Div<Vector3d> for f64 {
     type Output = Vector3d;
 
     fn div(self, v: Vector3d) -> Vector3d {
-        v * (1.0 / self)
+        Vector3d::new(self / v.x, self / v.y, self / v.z)
+    }
+}
+
+impl Sum for Vector3d {
+    fn sum<I: Iterator<Item = Vector3d>>(iter: I) -> Vector3d {
+        iter.fold(Vector3d::ZERO, |a, b| a + b)
+    }
+}
+
+impl<'a> Sum<&'a Vector3d> for Vector3d {
+    fn sum<I: Iterator<Item = &'a Vector3d>>(iter: I) -> Vector3d {
+        iter.fold(Vector3d::ZERO, |a, b| a + *b)
     }
 }
 
@@ -162,3 +375,267 @@ impl Neg for Vector3d {
         Vector3d::new(-self.x, -self.y, -self.z)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts;
+    use rng::SplitMix64;
+
+    fn assert_close(a: Vector3d, b: Vector3d) {
+        assert!((a - b).length() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn div_by_scalar() {
+        assert_eq!(Vector3d::new(2.0, 4.0, 6.0) / 2.0, Vector3d::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn div_component_wise() {
+        assert_eq!(Vector3d::new(2.0, 6.0, -8.0) / Vector3d::new(2.0, 3.0, 4.0), Vector3d::new(1.0, 2.0, -2.0));
+    }
+
+    #[test]
+    fn scalar_div_component_wise() {
+        assert_eq!(12.0 / Vector3d::new(2.0, 3.0, -4.0), Vector3d::new(6.0, 4.0, -3.0));
+    }
+
+    #[test]
+    fn normalize_divides_by_length() {
+        assert_close(Vector3d::new(0.0, 3.0, 4.0).normalize(), Vector3d::new(0.0, 0.6, 0.8));
+    }
+
+    #[test]
+    fn sub_assign() {
+        let mut v = Vector3d::new(1.0, 2.0, 3.0);
+        v -= Vector3d::new(0.5, 2.0, 4.0);
+        assert_eq!(v, Vector3d::new(0.5, 0.0, -1.0));
+        let mut w = Vector3d::new(1.0, -2.0, 3.0);
+        w -= w;
+        assert_eq!(w, Vector3d::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn mul_assign() {
+        let mut v = Vector3d::new(1.0, 2.0, 3.0);
+        v *= Vector3d::new(2.0, 0.5, -1.0);
+        assert_eq!(v, Vector3d::new(2.0, 1.0, -3.0));
+        let mut w = Vector3d::new(1.0, -2.0, 3.0);
+        w *= 2.0;
+        assert_eq!(w, Vector3d::new(2.0, -4.0, 6.0));
+    }
+
+    #[test]
+    fn length_squared() {
+        assert_eq!(Vector3d::new(3.0, 4.0, 0.0).length_squared(), 25.0);
+        assert_eq!(Vector3d::new(3.0, 4.0, 0.0).length(), 5.0);
+    }
+
+    #[test]
+    fn reflect() {
+        assert_eq!(Vector3d::new(1.0, -1.0, 0.0).reflect(Vector3d::new(0.0, 1.0, 0.0)), Vector3d::new(1.0, 1.0, 0.0));
+        assert_eq!(Vector3d::new(0.0, 0.0, 1.0).reflect(Vector3d::new(0.0, 0.0, -1.0)), Vector3d::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn refract_straight_through() {
+        let d = Vector3d::new(1.0, -1.0, 0.0).normalize();
+        assert_close(d.refract(Vector3d::new(0.0, 1.0, 0.0), 1.0).unwrap(), d);
+    }
+
+    #[test]
+    fn refract_total_internal_reflection() {
+        let d = Vector3d::new(1.0, -0.1, 0.0).normalize();
+        assert_eq!(d.refract(Vector3d::new(0.0, 1.0, 0.0), 1.5), None);
+    }
+
+    #[test]
+    fn refract_follows_snells_law() {
+        let d = Vector3d::new(1.0, -1.0, 0.0).normalize();
+        let t: Vector3d = d.refract(Vector3d::new(0.0, 1.0, 0.0), 1.0 / 1.5).unwrap();
+        assert!((t.length() - 1.0).abs() < 1e-12);
+        assert!((t.x - consts::FRAC_1_SQRT_2 / 1.5).abs() < 1e-12);
+        assert!(t.y < 0.0);
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Vector3d::new(1.0, 2.0, 3.0);
+        let b = Vector3d::new(3.0, -2.0, 5.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), (a + b) / 2.0);
+        assert_eq!(a.lerp(b, 2.0), Vector3d::new(5.0, -6.0, 7.0));
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(Vector3d::new(0.0, 0.0, 0.0).distance(Vector3d::new(0.0, 3.0, 4.0)), 5.0);
+        assert_eq!(Vector3d::new(1.0, 1.0, 1.0).distance_squared(Vector3d::new(1.0, 4.0, 5.0)), 25.0);
+    }
+
+    #[test]
+    fn try_normalize() {
+        assert_eq!(Vector3d::new(0.0, 0.0, 2.0).try_normalize(), Some(Vector3d::new(0.0, 0.0, 1.0)));
+        assert_eq!(Vector3d::new(0.0, 0.0, 0.0).try_normalize(), None);
+        assert_eq!(Vector3d::new(1e-20, 0.0, 0.0).try_normalize(), None);
+    }
+
+    #[test]
+    fn array_round_trip() {
+        let a: [f64; 3] = [1.5, -2.0, 3.25];
+        let v = Vector3d::from(a);
+        assert_eq!(v, Vector3d::new(1.5, -2.0, 3.25));
+        assert_eq!(v.to_array(), a);
+        let b: [f64; 3] = v.into();
+        assert_eq!(b, a);
+    }
+
+    #[test]
+    fn index() {
+        let mut v = Vector3d::new(1.0, 2.0, 3.0);
+        assert_eq!((v[0], v[1], v[2]), (v.x, v.y, v.z));
+        v[1] = 5.0;
+        assert_eq!(v, Vector3d::new(1.0, 5.0, 3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Vector3d index out of range: 3")]
+    fn index_out_of_range() {
+        let v = Vector3d::new(0.0, 0.0, 0.0);
+        let _ = v[3];
+    }
+
+    #[test]
+    fn clamp() {
+        assert_eq!(Vector3d::new(300.0, -5.0, 128.0).clamp_scalar(0.0, 255.0), Vector3d::new(255.0, 0.0, 128.0));
+        let (lo, hi) = (Vector3d::new(0.0, 1.0, 2.0), Vector3d::new(1.0, 2.0, 3.0));
+        assert_eq!(Vector3d::new(-1.0, 1.5, 4.0).clamp(lo, hi), Vector3d::new(0.0, 1.5, 3.0));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Vector3d::new(1.0, 2.0, 3.0).to_string(), "(1.000, 2.000, 3.000)");
+        assert_eq!(format!("{}", Vector3d::new(-0.5, 0.12345, 10.0)), "(-0.500, 0.123, 10.000)");
+    }
+
+    #[test]
+    fn default_is_zero() {
+        assert_eq!(Vector3d::default(), Vector3d::ZERO);
+        assert_eq!(Vector3d::ONE, Vector3d::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn angle_between() {
+        let x = Vector3d::new(1.0, 0.0, 0.0);
+        assert!((x.angle_between(Vector3d::new(0.0, 0.0, 3.0)) - consts::FRAC_PI_2).abs() < 1e-12);
+        assert_eq!(x.angle_between(x * 2.0), 0.0);
+        assert!((x.angle_between(-x) - consts::PI).abs() < 1e-12);
+        // Rounding must not push the cosine past 1
+        let v = Vector3d::new(0.1, 0.2, 0.3);
+        assert!(!v.angle_between(v * 3.0).is_nan());
+    }
+
+    #[test]
+    fn project_and_reject() {
+        let v = Vector3d::new(2.0, 2.0, 0.0);
+        let x = Vector3d::new(1.0, 0.0, 0.0);
+        assert_eq!(v.project_onto(x), Vector3d::new(2.0, 0.0, 0.0));
+        assert_eq!(v.reject_from(x), Vector3d::new(0.0, 2.0, 0.0));
+        let onto = Vector3d::new(1.0, 2.0, 2.0);
+        assert_close(v.project_onto(onto) + v.reject_from(onto), v);
+        assert!(v.reject_from(onto).dot(onto).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sum() {
+        let vs = vec![Vector3d::new(1.0, 2.0, 3.0), Vector3d::new(-1.0, 0.5, 0.0), Vector3d::new(0.0, 0.5, 1.0)];
+        assert_eq!(vs.iter().sum::<Vector3d>(), Vector3d::new(0.0, 3.0, 4.0));
+        assert_eq!(vs.into_iter().sum::<Vector3d>(), Vector3d::new(0.0, 3.0, 4.0));
+        assert_eq!(Vec::<Vector3d>::new().into_iter().sum::<Vector3d>(), Vector3d::ZERO);
+    }
+
+    #[test]
+    fn orthonormal_basis() {
+        for &n in &[Vector3d::new(1.0, 0.0, 0.0), Vector3d::new(0.0, -1.0, 0.0), Vector3d::new(0.0, 0.0, 1.0),
+                    Vector3d::new(1.0, 2.0, -3.0).normalize(), Vector3d::new(-0.3, 0.3, 0.9).normalize()] {
+            let (t, b) = n.orthonormal_basis();
+            for &v in &[n, t, b] {
+                assert!((v.length() - 1.0).abs() < 1e-12, "{} in basis of {}", v, n);
+            }
+            assert!(n.dot(t).abs() < 1e-12 && n.dot(b).abs() < 1e-12 && t.dot(b).abs() < 1e-12, "basis of {}", n);
+        }
+    }
+
+    #[test]
+    fn rotate_around_axis() {
+        let x = Vector3d::new(1.0, 0.0, 0.0);
+        let z = Vector3d::new(0.0, 0.0, 1.0);
+        assert_close(x.rotate_around_axis(z, consts::FRAC_PI_2), Vector3d::new(0.0, 1.0, 0.0));
+        let v = Vector3d::new(1.0, 2.0, 3.0);
+        assert_eq!(v.rotate_around_axis(z, 0.0), v);
+        assert_close(v.rotate_around_axis(v.normalize(), 1.0), v);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn deserializes_from_a_sequence() {
+        use serde::de::value::{Error, SeqDeserializer};
+
+        let seq: SeqDeserializer<_, Error> = SeqDeserializer::new(vec![1.5, -2.0, 0.25].into_iter());
+        assert_eq!(Vector3d::deserialize(seq).unwrap(), Vector3d::new(1.5, -2.0, 0.25));
+    }
+
+    #[test]
+    fn cosine_hemisphere_samples_cluster_around_normal() {
+        let normal = Vector3d::new(1.0, 2.0, -2.0).normalize();
+        let mut rng = SplitMix64(7);
+        let n: usize = 20000;
+        let mut sum = Vector3d::ZERO;
+        for _ in 0..n {
+            let d: Vector3d = Vector3d::sample_cosine_hemisphere(normal, &mut rng);
+            assert!((d.length() - 1.0).abs() < 1e-12);
+            assert!(d.dot(normal) >= 0.0);
+            sum += d;
+        }
+        let mean: Vector3d = sum / n as f64;
+        assert!((mean.dot(normal) - 2.0 / 3.0).abs() < 0.01, "{}", mean);
+        assert!(mean.reject_from(normal).length() < 0.02, "{}", mean);
+    }
+
+    #[test]
+    fn is_finite() {
+        assert!(Vector3d::new(1.0, -2.0, 1e300).is_finite());
+        assert!(!Vector3d::new(f64::NAN, 0.0, 0.0).is_finite());
+        assert!(!Vector3d::new(0.0, f64::INFINITY, 0.0).is_finite());
+        assert!(!Vector3d::new(0.0, 0.0, f64::NEG_INFINITY).is_finite());
+    }
+
+    #[test]
+    fn max_component() {
+        assert_eq!(Vector3d::new(3.0, 1.0, 2.0).max_component(), (0, 3.0));
+        assert_eq!(Vector3d::new(1.0, 3.0, 2.0).max_component(), (1, 3.0));
+        assert_eq!(Vector3d::new(1.0, 2.0, 3.0).max_component(), (2, 3.0));
+        assert_eq!(Vector3d::new(1.0, 3.0, 3.0).max_component(), (1, 3.0));
+        assert_eq!(Vector3d::new(2.0, 2.0, 2.0).max_component(), (0, 2.0));
+    }
+
+    #[test]
+    fn min_component() {
+        assert_eq!(Vector3d::new(-3.0, 1.0, 2.0).min_component(), (0, -3.0));
+        assert_eq!(Vector3d::new(1.0, -3.0, 2.0).min_component(), (1, -3.0));
+        assert_eq!(Vector3d::new(1.0, 2.0, -3.0).min_component(), (2, -3.0));
+        assert_eq!(Vector3d::new(1.0, -3.0, -3.0).min_component(), (1, -3.0));
+        assert_eq!(Vector3d::new(2.0, 2.0, 2.0).min_component(), (0, 2.0));
+    }
+
+    #[test]
+    fn from_spherical() {
+        assert_close(Vector3d::from_spherical(2.0, 0.0, 1.0), Vector3d::new(0.0, 2.0, 0.0));
+        assert_close(Vector3d::from_spherical(2.0, consts::PI, 1.0), Vector3d::new(0.0, -2.0, 0.0));
+        assert_close(Vector3d::from_spherical(2.0, consts::FRAC_PI_2, 0.0), Vector3d::new(2.0, 0.0, 0.0));
+        assert_close(Vector3d::from_spherical(2.0, consts::FRAC_PI_2, consts::FRAC_PI_2), Vector3d::new(0.0, 0.0, 2.0));
+        assert_close(Vector3d::from_spherical(2.0, consts::FRAC_PI_2, consts::PI), Vector3d::new(-2.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,22 @@
+//! Renders a scene through the public API only, as a dependent crate would.
+
+extern crate raytracer;
+
+use raytracer::{render, Camera, Image, Material, RenderConfig, SampleMode, Sphere, Vector3d};
+
+#[test]
+fn renders_one_sphere() {
+    let sphere = Sphere::new(Vector3d::new(0.0, 0.0, 4.0), 1.0, Material::from_color(Vector3d::new(1.0, 0.5, 0.25)));
+    let camera = Camera::new(Vector3d::ZERO, Vector3d::new(0.0, 0.0, 1.0), Vector3d::new(0.0, 1.0, 0.0), 60.0);
+    let config = RenderConfig { width: 16, height: 12, sampling: SampleMode::Uniform(1), ..RenderConfig::default() };
+    let image: Image = render(&sphere, &camera, &config);
+    assert_eq!((image.width, image.height), (16, 12));
+    assert_eq!(image.pixels.len(), 16 * 12);
+    // The sphere fills the middle of the image, the black background the
+    // corners
+    let center: [u8; 3] = image.pixels[6 * 16 + 8];
+    assert!(center[0] > center[1] && center[1] > center[2], "{:?}", center);
+    for &k in &[0, 15, 11 * 16, 11 * 16 + 15] {
+        assert_eq!(image.pixels[k], [0, 0, 0]);
+    }
+}